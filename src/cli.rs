@@ -0,0 +1,229 @@
+use crate::config::{AppConfig, RouteAction};
+use crate::server::{
+    admin::redact_config,
+    matcher::RouteMatcher,
+    routes::{is_method_allowed, upstream_url},
+};
+use anyhow::{Result, anyhow};
+use axum::http::{Method, Uri};
+use clap::{Arg, ArgAction, Command};
+use std::{net::IpAddr, path::PathBuf};
+
+pub fn command() -> Command {
+    Command::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about(env!("CARGO_PKG_DESCRIPTION"))
+        .arg(
+            Arg::new("config")
+                .short('c')
+                .long("config")
+                .value_name("FILE")
+                .help("Path to the config file")
+                .value_parser(clap::value_parser!(PathBuf))
+                .required(false)
+                .global(true),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Start the gateway (the default without a subcommand)")
+                .arg(
+                    Arg::new("host")
+                        .long("host")
+                        .value_name("ADDR")
+                        .help("Override server.host, unless server.listeners is set")
+                        .value_parser(clap::value_parser!(IpAddr)),
+                )
+                .arg(
+                    Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .value_name("PORT")
+                        .help("Override server.port, unless server.listeners is set")
+                        .value_parser(clap::value_parser!(u16)),
+                )
+                .arg(
+                    Arg::new("log-level")
+                        .long("log-level")
+                        .value_name("LEVEL")
+                        .help("Override logging.level, e.g. info or sag=debug"),
+                )
+                .arg(
+                    Arg::new("debug")
+                        .long("debug")
+                        .help("Enable debug mode")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("validate").about("Check the config file and exit"))
+        .subcommand(Command::new("routes").about("Print the compiled route table"))
+        .subcommand(
+            Command::new("match")
+                .about("Show which route a request would hit and where it would be sent")
+                .arg(Arg::new("method").value_name("METHOD").required(true))
+                .arg(
+                    Arg::new("url")
+                        .value_name("URL")
+                        .help("Full URL or path, with an optional query string")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Inspect the configuration")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("dump")
+                        .about("Print the effective config after includes and env overrides")
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .help("Print JSON instead of YAML")
+                                .action(ArgAction::SetTrue),
+                        ),
+                ),
+        )
+        .subcommand(Command::new("schema").about("Print the JSON Schema for the config file"))
+        .subcommand(Command::new("version").about("Print the version"))
+}
+
+pub fn print_routes(config: AppConfig) -> Result<()> {
+    let matcher = RouteMatcher::new(config.routes).map_err(|e| anyhow!("{}", e))?;
+
+    let mut rows = vec![[
+        "#".to_string(),
+        "TYPE".to_string(),
+        "METHODS".to_string(),
+        "PATH".to_string(),
+        "TARGET".to_string(),
+        "SOURCE".to_string(),
+    ]];
+    for (summary, route) in matcher.summaries().into_iter().zip(matcher.routes()) {
+        rows.push([
+            summary.index.to_string(),
+            format!("{:?}", summary.match_type),
+            methods(&summary.methods),
+            summary.pattern.unwrap_or(summary.path),
+            match route.action {
+                RouteAction::Proxy => summary.target,
+                action => format!("[{}]", action.name()),
+            },
+            summary
+                .source
+                .map_or_else(|| "-".to_string(), |path| path.display().to_string()),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in &rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+
+    Ok(())
+}
+
+pub fn print_match(config: AppConfig, method: &str, url: &str) -> Result<()> {
+    let method = Method::from_bytes(method.to_uppercase().as_bytes())
+        .map_err(|_| anyhow!("Invalid method: {}", method))?;
+    let uri: Uri = url
+        .parse()
+        .map_err(|e| anyhow!("Invalid URL {}: {}", url, e))?;
+    let matcher = RouteMatcher::new(config.routes).map_err(|e| anyhow!("{}", e))?;
+
+    println!(
+        "{} {}",
+        method,
+        uri.path_and_query().map_or("/", |pq| pq.as_str())
+    );
+    let Some((index, route_match)) = matcher.find_indexed(uri.path(), uri.query()) else {
+        return Err(anyhow!("No route matches"));
+    };
+    let route = &route_match.route;
+
+    println!(
+        "  route:   #{} {:?} {} ({})",
+        index,
+        route.match_type,
+        route.path,
+        route
+            .source
+            .as_deref()
+            .map_or_else(|| "-".to_string(), |path| path.display().to_string())
+    );
+    if !route_match.params.is_empty() {
+        let mut params: Vec<String> = route_match
+            .params
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        params.sort();
+        println!("  params:  {}", params.join(", "));
+    }
+    println!("  methods: {}", methods(&route.methods));
+    println!("  action:  {}", route.action.name());
+
+    match &route.action {
+        RouteAction::Proxy => println!("  target:  {}", upstream_url(&route_match, &uri)?),
+        RouteAction::Redirect { status, location } => {
+            println!("  redirect: {} {}", status, route_match.render(location))
+        }
+        _ => {}
+    }
+
+    if !is_method_allowed(&route.methods, &method) {
+        return Err(anyhow!("Method {} is not allowed on this route", method));
+    }
+    Ok(())
+}
+
+pub fn dump_config(config: AppConfig, json: bool) -> Result<()> {
+    let config = redact_config(serde_json::to_value(&config)?);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+    } else {
+        print!("{}", serde_yaml::to_string(&config)?);
+    }
+    Ok(())
+}
+
+fn methods(methods: &[String]) -> String {
+    if methods.is_empty() {
+        "*".to_string()
+    } else {
+        methods.join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        command().debug_assert();
+
+        let matches = command()
+            .try_get_matches_from(["sag", "run", "--port", "9000", "-c", "gw.yaml", "--debug"])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<PathBuf>("config"),
+            Some(&PathBuf::from("gw.yaml"))
+        );
+        let (name, run) = matches.subcommand().unwrap();
+        assert_eq!(name, "run");
+        assert_eq!(run.get_one::<u16>("port"), Some(&9000));
+        assert!(run.get_flag("debug"));
+
+        assert!(command().try_get_matches_from(["sag", "config"]).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum RouteAction {
+    #[default]
+    Proxy, // Forward to the route target (default)
+
+    Redirect {
+        #[serde(default = "default_redirect_status")]
+        status: u16,
+
+        /// Location template, `{param}` placeholders are substituted
+        location: String,
+    },
+
+    Static {
+        #[serde(default = "default_static_status")]
+        status: u16,
+
+        #[serde(default)]
+        headers: HashMap<String, String>,
+
+        /// Inline body, takes precedence over `file`
+        #[serde(default)]
+        body: Option<String>,
+
+        #[serde(default)]
+        file: Option<PathBuf>,
+    },
+
+    Maintenance {
+        #[serde(default = "default_maintenance_status")]
+        status: u16,
+
+        /// Sent as Retry-After (seconds) when set
+        #[serde(default)]
+        retry_after: Option<u64>,
+
+        #[serde(default = "default_maintenance_content_type")]
+        content_type: String,
+
+        #[serde(default)]
+        body: Option<String>,
+    },
+
+    Directory {
+        root: PathBuf,
+
+        /// Leading path removed before resolving files under `root`
+        #[serde(default)]
+        strip_prefix: Option<String>,
+
+        /// Serve index.html for directory requests
+        #[serde(default = "default_true")]
+        index: bool,
+
+        /// Serve index.html for unknown paths (single page apps)
+        #[serde(default)]
+        spa_fallback: bool,
+
+        /// Prefer `.br`/`.gz` siblings when the client accepts them
+        #[serde(default = "default_true")]
+        precompressed: bool,
+    },
+}
+
+impl RouteAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RouteAction::Proxy => "Proxy",
+            RouteAction::Redirect { .. } => "Redirect",
+            RouteAction::Static { .. } => "Static",
+            RouteAction::Maintenance { .. } => "Maintenance",
+            RouteAction::Directory { .. } => "Directory",
+        }
+    }
+}
+
+fn default_redirect_status() -> u16 {
+    302
+}
+
+fn default_static_status() -> u16 {
+    200
+}
+
+fn default_maintenance_status() -> u16 {
+    503
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_maintenance_content_type() -> String {
+    "text/html; charset=utf-8".to_string()
+}
@@ -1,10 +1,25 @@
+use crate::config::AdminConfig;
+use crate::config::CacheConfig;
+use crate::config::CompressionConfig;
+use crate::config::CorsConfig;
+use crate::config::ErrorPagesConfig;
+use crate::config::ExplainConfig;
+use crate::config::HealthConfig;
+use crate::config::MetricsConfig;
+use crate::config::RequestIdConfig;
 use crate::config::RouteConfig;
+use crate::config::RouteGroup;
 use crate::config::ServerConfig;
+use crate::config::UpstreamConfig;
 use crate::logging::LoggingConfig;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+/// Gateway configuration, usually loaded from config.yaml
+#[derive(Debug, Deserialize, Serialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct AppConfig {
     #[serde(default)]
     pub server: ServerConfig,
@@ -12,9 +27,54 @@ pub struct AppConfig {
     #[serde(default)]
     pub routes: Vec<RouteConfig>,
 
+    /// Routes sharing a path prefix and defaults, placed after `routes`
+    #[serde(default)]
+    pub groups: Vec<RouteGroup>,
+
+    /// Backends that routes can reference by name
+    #[serde(default)]
+    pub upstreams: HashMap<String, UpstreamConfig>,
+
+    /// Glob patterns of route files, relative to this file, appended to `routes`
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Environment variables the included files may reference; they can't read files
+    #[serde(default)]
+    pub include_env: Vec<String>,
+
     #[serde(default)]
     pub logging: LoggingConfig,
 
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    #[serde(default)]
+    pub cors: CorsConfig,
+
+    #[serde(default)]
+    pub request_id: RequestIdConfig,
+
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    #[serde(default)]
+    pub admin: AdminConfig,
+
+    #[serde(default)]
+    pub health: HealthConfig,
+
+    #[serde(default)]
+    pub explain: ExplainConfig,
+
+    /// Bodies for gateway errors, overridable per route
+    #[serde(default)]
+    pub errors: ErrorPagesConfig,
+
+    /// Enable debug mode; error responses then include details
     #[serde(default)]
     pub debug: bool,
 }
@@ -0,0 +1,59 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Upper bound for all cached bodies, in bytes
+    #[serde(default = "default_max_size")]
+    pub max_size: usize,
+
+    /// Responses larger than this are never cached, in bytes
+    #[serde(default = "default_max_entry_size")]
+    pub max_entry_size: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RouteCacheConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Overrides the upstream freshness lifetime, in seconds
+    #[serde(default)]
+    pub ttl: Option<u64>,
+}
+
+fn default_max_size() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_max_entry_size() -> usize {
+    1024 * 1024
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size: default_max_size(),
+            max_entry_size: default_max_entry_size(),
+        }
+    }
+}
+
+impl Default for RouteCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            ttl: None,
+        }
+    }
+}
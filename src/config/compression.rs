@@ -0,0 +1,106 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CompressionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Responses smaller than this are sent as-is, in bytes
+    #[serde(default = "default_min_size")]
+    pub min_size: usize,
+
+    /// Larger responses, and those of unknown length, are sent as-is, in bytes
+    #[serde(default = "default_max_size")]
+    pub max_size: usize,
+
+    /// Limit for decompressed request bodies, before and after decoding, in bytes
+    #[serde(default = "default_max_request_size")]
+    pub max_request_size: usize,
+
+    /// Content types eligible for compression (matched without parameters)
+    #[serde(default = "default_content_types")]
+    pub content_types: Vec<String>,
+
+    /// Encodings offered to clients, in order of preference
+    #[serde(default = "default_encodings")]
+    pub encodings: Vec<Encoding>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RouteCompressionConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Decompress request bodies before forwarding them upstream
+    #[serde(default)]
+    pub decompress_requests: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum Encoding {
+    Brotli,
+    Zstd,
+    Gzip,
+}
+
+fn default_min_size() -> usize {
+    1024
+}
+
+fn default_max_size() -> usize {
+    8 * 1024 * 1024
+}
+
+fn default_max_request_size() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_content_types() -> Vec<String> {
+    [
+        "text/html",
+        "text/plain",
+        "text/css",
+        "text/javascript",
+        "application/javascript",
+        "application/json",
+        "application/problem+json",
+        "application/xml",
+        "image/svg+xml",
+    ]
+    .iter()
+    .map(|t| t.to_string())
+    .collect()
+}
+
+fn default_encodings() -> Vec<Encoding> {
+    vec![Encoding::Brotli, Encoding::Zstd, Encoding::Gzip]
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_size: default_min_size(),
+            max_size: default_max_size(),
+            max_request_size: default_max_request_size(),
+            content_types: default_content_types(),
+            encodings: default_encodings(),
+        }
+    }
+}
+
+impl Default for RouteCompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            decompress_requests: false,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CorsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Exact origins, "*" or wildcard subdomains like "https://*.example.com"
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+
+    /// Defaults to the route methods when empty
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+
+    /// Request headers allowed in preflights, "*" allows any
+    #[serde(default = "default_allowed_headers")]
+    pub allowed_headers: Vec<String>,
+
+    #[serde(default)]
+    pub exposed_headers: Vec<String>,
+
+    #[serde(default)]
+    pub allow_credentials: bool,
+
+    /// How long browsers may cache preflight results, in seconds
+    #[serde(default = "default_max_age")]
+    pub max_age: u64,
+}
+
+fn default_allowed_headers() -> Vec<String> {
+    vec![
+        "accept".to_string(),
+        "authorization".to_string(),
+        "content-type".to_string(),
+    ]
+}
+
+fn default_max_age() -> u64 {
+    600
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed_origins: Vec::new(),
+            allowed_methods: Vec::new(),
+            allowed_headers: default_allowed_headers(),
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: default_max_age(),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ErrorPagesConfig {
+    /// Body format for errors without a page of their own
+    #[serde(default)]
+    pub format: ErrorFormat,
+
+    /// Custom bodies keyed by status code ("404") or class ("5xx")
+    #[serde(default)]
+    pub pages: HashMap<String, ErrorPage>,
+
+    /// Replace the bodies of upstream 5xx responses that have a page
+    #[serde(default)]
+    pub map_upstream_5xx: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum ErrorFormat {
+    /// `{"error": ..., "request_id": ...}`
+    #[default]
+    Json,
+    /// Minimal HTML page
+    Html,
+    /// RFC 7807 `application/problem+json`
+    Problem,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ErrorPage {
+    /// Template with {status}, {error}, {detail} and {request_id} placeholders
+    #[serde(default)]
+    pub body: Option<String>,
+
+    /// Template read from disk, used when `body` is unset
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+
+    /// Defaults to the content type of `format`
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+impl ErrorFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ErrorFormat::Json => "application/json",
+            ErrorFormat::Html => "text/html; charset=utf-8",
+            ErrorFormat::Problem => "application/problem+json",
+        }
+    }
+}
+
+impl ErrorPagesConfig {
+    /// Page for a status, preferring the exact code over its class
+    pub fn page(&self, status: u16) -> Option<&ErrorPage> {
+        self.pages
+            .get(&status.to_string())
+            .or_else(|| self.pages.get(&format!("{}xx", status / 100)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_lookup() {
+        let config: ErrorPagesConfig = serde_yaml::from_str(
+            r#"
+pages:
+  "502": { body: "bad gateway" }
+  "5xx": { body: "server error" }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.page(502).unwrap().body.as_deref(),
+            Some("bad gateway")
+        );
+        assert_eq!(
+            config.page(503).unwrap().body.as_deref(),
+            Some("server error")
+        );
+        assert!(config.page(404).is_none());
+    }
+}
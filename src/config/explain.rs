@@ -0,0 +1,40 @@
+use ipnet::IpNet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExplainConfig {
+    /// Answer requests carrying `header` with a routing trace instead of proxying
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_header")]
+    pub header: String,
+
+    /// Peers allowed to request a trace; it exposes routes and upstream URLs
+    #[serde(default = "default_trusted_networks")]
+    #[schemars(with = "Vec<String>")]
+    pub trusted_networks: Vec<IpNet>,
+}
+
+fn default_header() -> String {
+    "x-sag-explain".to_string()
+}
+
+fn default_trusted_networks() -> Vec<IpNet> {
+    vec![
+        "127.0.0.0/8".parse().expect("valid network"),
+        "::1/128".parse().expect("valid network"),
+    ]
+}
+
+impl Default for ExplainConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            header: default_header(),
+            trusted_networks: default_trusted_networks(),
+        }
+    }
+}
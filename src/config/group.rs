@@ -0,0 +1,112 @@
+use crate::config::{MatchType, RouteConfig};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Routes that share a path prefix and default settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RouteGroup {
+    /// Prepended to the path of every member route
+    #[serde(default)]
+    pub prefix: String,
+
+    /// Route fields members inherit; a member setting a field replaces it whole
+    #[serde(default)]
+    #[schemars(with = "RouteConfig")]
+    pub defaults: Map<String, Value>,
+
+    #[serde(default)]
+    #[schemars(with = "Vec<RouteConfig>")]
+    pub routes: Vec<Map<String, Value>>,
+}
+
+impl RouteGroup {
+    /// Member routes with the defaults applied and the prefix prepended
+    pub fn flatten(&self) -> Result<Vec<RouteConfig>, String> {
+        self.routes
+            .iter()
+            .enumerate()
+            .map(|(i, member)| {
+                let mut fields = self.defaults.clone();
+                fields.extend(member.clone());
+
+                let mut route: RouteConfig = serde_json::from_value(Value::Object(fields))
+                    .map_err(|e| format!("routes[{}]: {}", i, e))?;
+                route.path = prefixed_path(&self.prefix, &route.path, &route.match_type);
+                Ok(route)
+            })
+            .collect()
+    }
+}
+
+fn prefixed_path(prefix: &str, path: &str, match_type: &MatchType) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        return path.to_string();
+    }
+
+    match match_type {
+        // Match the prefix literally and only at the start of the path
+        MatchType::Regex => format!(
+            "^{}{}",
+            regex::escape(prefix),
+            path.strip_prefix('^').unwrap_or(path)
+        ),
+        _ if path.is_empty() => prefix.to_string(),
+        _ if path.starts_with('/') => format!("{}{}", prefix, path),
+        _ => format!("{}/{}", prefix, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::auth::AuthType;
+
+    #[test]
+    fn test_flatten() {
+        let group: RouteGroup = serde_yaml::from_str(
+            "
+prefix: /api/v1/
+defaults:
+  target: http://api
+  methods: [GET]
+  auth:
+    required: true
+routes:
+  - path: /users
+  - path: /orders
+    methods: [GET, POST]
+  - path: '^/items/(?P<id>\\d+)$'
+    match_type: Regex
+  - path: '/tags/\\w+'
+    match_type: Regex
+",
+        )
+        .unwrap();
+
+        let routes = group.flatten().unwrap();
+        assert_eq!(routes[0].path, "/api/v1/users");
+        assert_eq!(routes[0].methods, vec!["GET"]);
+        assert!(routes[0].auth.required);
+        assert!(matches!(routes[0].auth.auth_type, AuthType::Bearer));
+        assert_eq!(routes[1].methods, vec!["GET", "POST"]);
+        assert_eq!(routes[1].target, "http://api");
+        assert_eq!(routes[2].path, "^/api/v1/items/(?P<id>\\d+)$");
+        assert_eq!(routes[3].path, "^/api/v1/tags/\\w+");
+    }
+
+    #[test]
+    fn test_flatten_reports_member() {
+        let group: RouteGroup =
+            serde_yaml::from_str("routes:\n  - path: /a\n  - path: /b\n    methds: [GET]\n")
+                .unwrap();
+        let error = group.flatten().unwrap_err();
+        assert!(
+            error.starts_with("routes[1]: unknown field `methds`"),
+            "{}",
+            error
+        );
+    }
+}
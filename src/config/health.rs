@@ -0,0 +1,56 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HealthConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Serve the probes on the admin listener instead of the gateway port
+    #[serde(default)]
+    pub on_admin: bool,
+
+    #[serde(default = "default_liveness_path")]
+    pub liveness_path: String,
+
+    #[serde(default = "default_readiness_path")]
+    pub readiness_path: String,
+
+    /// Plain "OK" endpoint kept for older probes; null disables it
+    #[serde(default = "default_health_path")]
+    pub health_path: Option<String>,
+
+    /// Report not ready for this many seconds after startup
+    #[serde(default)]
+    pub warmup_secs: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_liveness_path() -> String {
+    "/livez".to_string()
+}
+
+fn default_readiness_path() -> String {
+    "/readyz".to_string()
+}
+
+fn default_health_path() -> Option<String> {
+    Some("/health".to_string())
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            on_admin: false,
+            liveness_path: default_liveness_path(),
+            readiness_path: default_readiness_path(),
+            health_path: default_health_path(),
+            warmup_secs: 0,
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::{fmt, fs};
+
+/// A `${...}` reference that could not be resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterpolationError {
+    pub reference: String,
+    pub message: String,
+}
+
+impl fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${{{}}}: {}", self.reference, self.message)
+    }
+}
+
+impl std::error::Error for InterpolationError {}
+
+/// References a document may expand
+#[derive(Debug, Clone, Copy)]
+pub enum Scope<'a> {
+    /// Any variable or file; for the main config
+    All,
+    /// Only the listed variables; for route files other teams own
+    Variables(&'a [String]),
+}
+
+/// Finds the value of a variable; `env_var` outside of tests
+pub type Lookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// Look a variable up in the process environment
+pub fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Expand `${VAR}`, `${VAR:-default}` and `${file:/path}` references.
+/// `$${` is kept as a literal `${`.
+pub fn interpolate_str(
+    input: &str,
+    scope: Scope,
+    lookup: Lookup,
+) -> Result<String, InterpolationError> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find('}').ok_or_else(|| InterpolationError {
+            reference: after.to_string(),
+            message: "missing closing brace".to_string(),
+        })?;
+        output.push_str(&resolve(&after[..end], scope, lookup)?);
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn resolve(reference: &str, scope: Scope, lookup: Lookup) -> Result<String, InterpolationError> {
+    let error = |message: String| InterpolationError {
+        reference: reference.to_string(),
+        message,
+    };
+
+    if let Scope::Variables(allowed) = scope {
+        let name = reference.split(":-").next().unwrap_or(reference);
+        if !allowed.iter().any(|variable| variable == name) {
+            return Err(error(
+                "not allowed in included files, list the variable in include_env".to_string(),
+            ));
+        }
+    }
+
+    if let Some(path) = reference.strip_prefix("file:") {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| error(format!("failed to read {}: {}", path, e)))?;
+        // Secret files usually end with a newline that isn't part of the value
+        let value = contents.strip_suffix('\n').unwrap_or(&contents);
+        return Ok(value.strip_suffix('\r').unwrap_or(value).to_string());
+    }
+
+    let (name, default) = match reference.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (reference, None),
+    };
+    if !is_variable_name(name) {
+        return Err(error("invalid variable name".to_string()));
+    }
+
+    // Like the shell, an empty variable also takes the default
+    match (lookup(name), default) {
+        (Some(value), Some(default)) if value.is_empty() => Ok(default.to_string()),
+        (Some(value), _) => Ok(value),
+        (None, Some(default)) => Ok(default.to_string()),
+        (None, None) => Err(error("environment variable is not set".to_string())),
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Interpolate every string in a YAML document, leaving keys alone.
+/// References outside the scope are reported as errors.
+pub fn interpolate_yaml(
+    value: &mut serde_yaml::Value,
+    scope: Scope,
+    lookup: Lookup,
+) -> Vec<InterpolationError> {
+    let mut errors = Vec::new();
+    walk_yaml(value, scope, lookup, &mut errors);
+    errors
+}
+
+fn walk_yaml(
+    value: &mut serde_yaml::Value,
+    scope: Scope,
+    lookup: Lookup,
+    errors: &mut Vec<InterpolationError>,
+) {
+    use serde_yaml::Value;
+
+    match value {
+        Value::String(s) => expand(s, scope, lookup, errors),
+        Value::Sequence(items) => items
+            .iter_mut()
+            .for_each(|item| walk_yaml(item, scope, lookup, errors)),
+        Value::Mapping(map) => map
+            .values_mut()
+            .for_each(|item| walk_yaml(item, scope, lookup, errors)),
+        Value::Tagged(tagged) => walk_yaml(&mut tagged.value, scope, lookup, errors),
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+/// Interpolate every string collected by the `config` crate
+pub fn interpolate_config(value: &mut config::Value, lookup: Lookup) -> Vec<InterpolationError> {
+    let mut errors = Vec::new();
+    walk_config(value, lookup, &mut errors);
+    errors
+}
+
+fn walk_config(value: &mut config::Value, lookup: Lookup, errors: &mut Vec<InterpolationError>) {
+    use config::ValueKind;
+
+    match &mut value.kind {
+        ValueKind::String(s) => expand(s, Scope::All, lookup, errors),
+        ValueKind::Array(items) => items
+            .iter_mut()
+            .for_each(|item| walk_config(item, lookup, errors)),
+        ValueKind::Table(map) => map
+            .values_mut()
+            .for_each(|item| walk_config(item, lookup, errors)),
+        _ => {}
+    }
+}
+
+/// All the errors in one message, so a single run reports every bad reference
+pub fn join_errors(errors: &[InterpolationError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Failed strings are left as written so every error can be reported
+fn expand(s: &mut String, scope: Scope, lookup: Lookup, errors: &mut Vec<InterpolationError>) {
+    if !s.contains("${") {
+        return;
+    }
+    match interpolate_str(s, scope, lookup) {
+        Ok(expanded) => *s = expanded,
+        Err(e) => errors.push(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // Tests use their own variables, changing the process environment is unsafe
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_interpolate_str() {
+        let lookup = vars(&[
+            ("SAG_TEST_UPSTREAM", "http://users:8080"),
+            ("SAG_TEST_EMPTY", ""),
+        ]);
+        let interpolate = |input| interpolate_str(input, Scope::All, &lookup);
+
+        assert_eq!(
+            interpolate("${SAG_TEST_UPSTREAM}/v1").unwrap(),
+            "http://users:8080/v1"
+        );
+        assert_eq!(
+            interpolate("${SAG_TEST_MISSING:-http://fallback}").unwrap(),
+            "http://fallback"
+        );
+        assert_eq!(interpolate("${SAG_TEST_EMPTY:-x}").unwrap(), "x");
+        assert_eq!(
+            interpolate("$${HOME} and {id}").unwrap(),
+            "${HOME} and {id}"
+        );
+
+        let error = interpolate("key=${SAG_TEST_MISSING}").unwrap_err();
+        assert_eq!(error.reference, "SAG_TEST_MISSING");
+        assert!(interpolate("${not valid}").is_err());
+        assert!(interpolate("${SAG_TEST_UPSTREAM").is_err());
+    }
+
+    #[test]
+    fn test_file_reference() {
+        let path = std::env::temp_dir().join(format!("sag-secret-{}", std::process::id()));
+        fs::write(&path, "s3cret\n").unwrap();
+
+        let reference = format!("${{file:{}}}", path.display());
+        assert_eq!(
+            interpolate_str(&reference, Scope::All, &vars(&[])).unwrap(),
+            "s3cret"
+        );
+        fs::remove_file(&path).unwrap();
+
+        assert!(interpolate_str(&reference, Scope::All, &vars(&[])).is_err());
+    }
+
+    #[test]
+    fn test_interpolate_yaml() {
+        let mut document: serde_yaml::Value = serde_yaml::from_str(
+            "routes:\n  - path: /a\n    target: ${SAG_TEST_YAML:-http://a}\n  - target: ${SAG_TEST_NOPE}\n",
+        )
+        .unwrap();
+
+        let errors = interpolate_yaml(&mut document, Scope::All, &vars(&[]));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(document["routes"][0]["target"], "http://a");
+        assert_eq!(document["routes"][1]["target"], "${SAG_TEST_NOPE}");
+    }
+
+    #[test]
+    fn test_scoped_references() {
+        let lookup = vars(&[("SAG_TEST_SCOPED", "http://orders"), ("HOME", "/root")]);
+        let allowed = ["SAG_TEST_SCOPED".to_string()];
+        let mut document: serde_yaml::Value = serde_yaml::from_str(
+            "routes:\n  - target: ${SAG_TEST_SCOPED}\n  - target: ${HOME}\n  - target: ${file:/etc/passwd}\n",
+        )
+        .unwrap();
+
+        let errors = interpolate_yaml(&mut document, Scope::Variables(&allowed), &lookup);
+        assert_eq!(document["routes"][0]["target"], "http://orders");
+        let rejected: Vec<&str> = errors.iter().map(|e| e.reference.as_str()).collect();
+        assert_eq!(rejected, vec!["HOME", "file:/etc/passwd"]);
+    }
+}
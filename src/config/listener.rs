@@ -0,0 +1,122 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{fmt, net::SocketAddr, path::PathBuf, str::FromStr};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerConfig {
+    /// Referenced by the `listeners` of routes; defaults to the address
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// "host:port", "unix:/path/to.sock" or "systemd:N" for the Nth socket
+    /// passed by systemd socket activation
+    #[schemars(with = "String")]
+    pub address: ListenAddress,
+
+    /// Terminate TLS on this listener
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first
+    pub cert: PathBuf,
+
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+    /// Index into the sockets passed with LISTEN_FDS
+    Systemd(usize),
+}
+
+impl ListenerConfig {
+    pub fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| self.address.to_string())
+    }
+}
+
+impl FromStr for ListenAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err("unix socket path is empty".to_string());
+            }
+            return Ok(ListenAddress::Unix(PathBuf::from(path)));
+        }
+        if let Some(index) = s.strip_prefix("systemd:") {
+            return index
+                .parse()
+                .map(ListenAddress::Systemd)
+                .map_err(|_| format!("invalid systemd socket index {:?}", index));
+        }
+        s.parse().map(ListenAddress::Tcp).map_err(|_| {
+            format!(
+                "invalid listen address {:?}, expected host:port, unix:PATH or systemd:N",
+                s
+            )
+        })
+    }
+}
+
+impl TryFrom<String> for ListenAddress {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ListenAddress> for String {
+    fn from(address: ListenAddress) -> Self {
+        address.to_string()
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(addr) => write!(f, "{}", addr),
+            ListenAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+            ListenAddress::Systemd(index) => write!(f, "systemd:{}", index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            "0.0.0.0:443".parse::<ListenAddress>().unwrap(),
+            ListenAddress::Tcp("0.0.0.0:443".parse().unwrap())
+        );
+        assert_eq!(
+            "unix:/run/sag.sock".parse::<ListenAddress>().unwrap(),
+            ListenAddress::Unix(PathBuf::from("/run/sag.sock"))
+        );
+        assert_eq!(
+            "systemd:1".parse::<ListenAddress>().unwrap(),
+            ListenAddress::Systemd(1)
+        );
+        assert!("localhost".parse::<ListenAddress>().is_err());
+        assert!("unix:".parse::<ListenAddress>().is_err());
+
+        let listener: ListenerConfig = serde_yaml::from_str("address: unix:/tmp/s.sock").unwrap();
+        assert_eq!(listener.name(), "unix:/tmp/s.sock");
+    }
+}
@@ -1,12 +1,30 @@
-use crate::config::AppConfig;
+use crate::config::{
+    AppConfig, RouteConfig, RouteGroup,
+    interpolate::{Scope, env_var, interpolate_config, interpolate_yaml, join_errors},
+    upstream::resolve_upstream,
+};
 use anyhow::{Context, Result, anyhow};
 use config::{Config, Environment, File};
 use once_cell::sync::Lazy;
-use std::path::Path;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
 
 // pub static ENV_PREFIX = "APP";
 pub static ENV_PREFIX: Lazy<String> = Lazy::new(|| env!("CARGO_PKG_NAME").to_uppercase());
 
+const DEFAULT_CONFIG_FILE: &str = "config.yaml";
+
+/// Shape of a file pulled in through `include`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouteFile {
+    #[serde(default)]
+    routes: Vec<RouteConfig>,
+
+    #[serde(default)]
+    groups: Vec<RouteGroup>,
+}
+
 pub fn load_config(path_opt: Option<&Path>) -> Result<AppConfig> {
     let mut builder = Config::builder();
 
@@ -20,14 +38,268 @@ pub fn load_config(path_opt: Option<&Path>) -> Result<AppConfig> {
             .ok_or_else(|| anyhow!("Invalid UTF-8 in path"))?;
         builder = builder.add_source(File::with_name(path_str).required(true));
     } else {
-        builder = builder.add_source(File::with_name("config.yaml").required(false));
+        builder = builder.add_source(File::with_name(DEFAULT_CONFIG_FILE).required(false));
     }
 
     builder = builder.add_source(Environment::with_prefix(&ENV_PREFIX).separator("__"));
 
     let config = builder.build().context("Failed to build configuration")?;
 
-    config
+    // Expand ${...} references before any value is interpreted
+    let mut root = config.cache;
+    let errors = interpolate_config(&mut root, &env_var);
+    if !errors.is_empty() {
+        return Err(anyhow!(
+            "Failed to interpolate configuration: {}",
+            join_errors(&errors)
+        ));
+    }
+
+    let mut app_config: AppConfig = root
         .try_deserialize()
-        .context("Failed to deserialize AppConfig")
+        .context("Failed to deserialize AppConfig")?;
+
+    // Groups are expanded into plain routes, which is all the server sees
+    let main_file = path_opt.unwrap_or(Path::new(DEFAULT_CONFIG_FILE));
+    let groups = std::mem::take(&mut app_config.groups);
+    app_config
+        .routes
+        .extend(flatten_groups(&groups, main_file)?);
+    if main_file.exists() && !routes_from_env() {
+        for route in &mut app_config.routes {
+            route.source = Some(main_file.to_path_buf());
+        }
+    }
+
+    for file in included_files(main_file, &app_config.include)? {
+        let routes = load_route_file(&file, &app_config.include_env)?;
+        app_config.routes.extend(routes);
+    }
+
+    for route in &mut app_config.routes {
+        resolve_upstream(route, &app_config.upstreams)
+            .map_err(|e| anyhow!("{} in {}", e, source_name(route)))?;
+    }
+
+    check_conflicts(&app_config.routes)?;
+    Ok(app_config)
+}
+
+// Environment variables replace the routes and groups of the file whole
+fn routes_from_env() -> bool {
+    let prefixes = ["ROUTES", "GROUPS"].map(|key| format!("{}__{}", *ENV_PREFIX, key));
+    std::env::vars_os().any(|(name, _)| {
+        let name = name.to_string_lossy().to_uppercase();
+        prefixes
+            .iter()
+            .any(|prefix| name.starts_with(prefix.as_str()))
+    })
+}
+
+/// Files matched by the `include` patterns, resolved relative to the config file
+pub fn included_files(config_file: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let base = match config_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let full = base.join(pattern);
+        let full = full
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid UTF-8 in include pattern: {}", pattern))?;
+        let entries =
+            glob::glob(full).with_context(|| format!("Invalid include pattern: {}", pattern))?;
+
+        let mut matched = false;
+        for entry in entries {
+            let file = entry.with_context(|| format!("Failed to read include {}", pattern))?;
+            matched = true;
+            if file.is_file() && !files.contains(&file) {
+                files.push(file);
+            }
+        }
+
+        // An empty directory is fine, a misspelled file name is not
+        if !matched && !pattern.contains(['*', '?', '[']) {
+            return Err(anyhow!("Included file does not exist: {}", full));
+        }
+    }
+
+    Ok(files)
+}
+
+// Route files may belong to other teams, so they only see allowed variables
+fn load_route_file(path: &Path, include_env: &[String]) -> Result<Vec<RouteConfig>> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read included file {}", path.display()))?;
+    // Parsing the raw text first keeps line numbers in structural errors
+    serde_yaml::from_str::<RouteFile>(&source)
+        .with_context(|| format!("Failed to load routes from {}", path.display()))?;
+
+    let mut document: serde_yaml::Value = serde_yaml::from_str(&source)?;
+    let errors = interpolate_yaml(&mut document, Scope::Variables(include_env), &env_var);
+    if !errors.is_empty() {
+        return Err(anyhow!(
+            "Failed to interpolate {}: {}",
+            path.display(),
+            join_errors(&errors)
+        ));
+    }
+    let file: RouteFile = serde_yaml::from_value(document)
+        .with_context(|| format!("Failed to load routes from {}", path.display()))?;
+
+    let mut routes = file.routes;
+    routes.extend(flatten_groups(&file.groups, path)?);
+    Ok(routes
+        .into_iter()
+        .map(|route| RouteConfig {
+            source: Some(path.to_path_buf()),
+            ..route
+        })
+        .collect())
+}
+
+fn flatten_groups(groups: &[RouteGroup], file: &Path) -> Result<Vec<RouteConfig>> {
+    let mut routes = Vec::new();
+    for (i, group) in groups.iter().enumerate() {
+        let members = group
+            .flatten()
+            .map_err(|e| anyhow!("groups[{}].{} in {}", i, e, file.display()))?;
+        routes.extend(members);
+    }
+    Ok(routes)
+}
+
+/// Two routes claiming the same path can't both be served, whatever their methods
+pub fn check_conflicts(routes: &[RouteConfig]) -> Result<()> {
+    for (i, route) in routes.iter().enumerate() {
+        for earlier in &routes[..i] {
+            if conflicts(earlier, route) {
+                return Err(anyhow!(
+                    "Route {:?} {:?} in {} conflicts with the same route in {}",
+                    route.match_type,
+                    route.path,
+                    source_name(route),
+                    source_name(earlier)
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn conflicts(a: &RouteConfig, b: &RouteConfig) -> bool {
+    // Query predicates let routes on the same path coexist
+    let same_type = std::mem::discriminant(&a.match_type) == std::mem::discriminant(&b.match_type);
+
+    // Like predicates, limiting a route to some listeners lets others fall through
+    let listeners_overlap = if a.listeners.is_empty() || b.listeners.is_empty() {
+        a.listeners.is_empty() && b.listeners.is_empty()
+    } else {
+        a.listeners.iter().any(|name| b.listeners.contains(name))
+    };
+
+    same_type
+        && a.path == b.path
+        && listeners_overlap
+        && a.query.predicates.is_empty()
+        && b.query.predicates.is_empty()
+}
+
+pub fn source_name(route: &RouteConfig) -> String {
+    match &route.source {
+        Some(path) => path.display().to_string(),
+        None => "the environment".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_includes() {
+        let dir = std::env::temp_dir().join(format!("sag-include-{}", std::process::id()));
+        let main = write(
+            &dir,
+            "gateway.yaml",
+            "include: [\"routes.d/*.yaml\"]\nroutes:\n  - path: /health-check\n    target: http://main\n",
+        );
+        write(
+            &dir,
+            "routes.d/users.yaml",
+            "routes:\n  - path: /users\n    target: http://users\n    match_type: Prefix\n",
+        );
+        write(
+            &dir,
+            "routes.d/orders.yaml",
+            "routes:\n  - path: /orders\n    target: http://orders\n",
+        );
+
+        let config = load_config(Some(&main)).unwrap();
+        let loaded: Vec<(&str, PathBuf)> = config
+            .routes
+            .iter()
+            .map(|r| (r.path.as_str(), r.source.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            loaded,
+            vec![
+                ("/health-check", main.clone()),
+                ("/orders", dir.join("routes.d/orders.yaml")),
+                ("/users", dir.join("routes.d/users.yaml")),
+            ]
+        );
+
+        // Another team claiming /users is rejected, naming both files
+        write(
+            &dir,
+            "routes.d/users-v2.yaml",
+            "routes:\n  - path: /users\n    target: http://users-v2\n    match_type: Prefix\n    methods: [GET]\n",
+        );
+        let error = format!("{:#}", load_config(Some(&main)).unwrap_err());
+        assert!(
+            error.contains("users.yaml conflicts with the same route in"),
+            "{}",
+            error
+        );
+        assert!(error.contains("users-v2.yaml"), "{}", error);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_include() {
+        let error = included_files(Path::new("config.yaml"), &["nope.yaml".to_string()]);
+        assert!(error.is_err());
+
+        let none = included_files(Path::new("config.yaml"), &["nope.d/*.yaml".to_string()]);
+        assert!(none.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_include_reports_every_reference() {
+        let dir = std::env::temp_dir().join(format!("sag-include-refs-{}", std::process::id()));
+        let file = write(
+            &dir,
+            "team.yaml",
+            "routes:\n  - path: /a\n    target: ${file:/etc/hostname}\n  - path: /b\n    target: ${HOME}\n",
+        );
+
+        let error = format!("{:#}", load_route_file(&file, &[]).unwrap_err());
+        assert!(error.contains("${file:/etc/hostname}"), "{}", error);
+        assert!(error.contains("${HOME}"), "{}", error);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,37 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_path")]
+    pub path: String,
+
+    /// Serve metrics on a separate listener instead of the gateway port
+    #[serde(default)]
+    pub listen: Option<SocketAddr>,
+
+    /// Without `listen`, serve metrics on the gateway listeners instead of
+    /// the admin API, where they need its token
+    #[serde(default)]
+    pub public: bool,
+}
+
+fn default_path() -> String {
+    "/metrics".to_string()
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_path(),
+            listen: None,
+            public: false,
+        }
+    }
+}
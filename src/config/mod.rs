@@ -4,11 +4,59 @@ pub use app::AppConfig;
 pub mod server;
 pub use server::ServerConfig;
 
+pub mod admin;
+pub use admin::AdminConfig;
+
+pub mod action;
+pub use action::RouteAction;
+
 pub mod auth;
 pub use auth::AuthConfig;
 
+pub mod cache;
+pub use cache::{CacheConfig, RouteCacheConfig};
+
+pub mod compression;
+pub use compression::{CompressionConfig, Encoding, RouteCompressionConfig};
+
+pub mod cors;
+pub use cors::CorsConfig;
+
+pub mod errors;
+pub use errors::{ErrorFormat, ErrorPage, ErrorPagesConfig};
+
+pub mod explain;
+pub use explain::ExplainConfig;
+
+pub mod group;
+pub use group::RouteGroup;
+
+pub mod listener;
+pub use listener::{ListenAddress, ListenerConfig, TlsConfig};
+
+pub mod health;
+pub use health::HealthConfig;
+
+pub mod metrics;
+pub use metrics::MetricsConfig;
+
+pub mod query;
+pub use query::{QueryConfig, QueryPredicate};
+
+pub mod request_id;
+pub use request_id::RequestIdConfig;
+
 pub mod route;
 pub use route::{MatchType, RouteConfig};
 
+pub mod upstream;
+pub use upstream::UpstreamConfig;
+
+pub mod interpolate;
+
+pub mod schema;
+
+pub mod validate;
+
 pub mod loader;
 pub use loader::load_config;
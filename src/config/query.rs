@@ -0,0 +1,53 @@
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QueryConfig {
+    /// Predicates that must all hold for the route to match
+    #[serde(default, rename = "match")]
+    pub predicates: Vec<QueryPredicate>,
+
+    /// Parameters dropped before forwarding
+    #[serde(default)]
+    pub remove: Vec<String>,
+
+    /// Parameters renamed before forwarding (old name -> new name), applied
+    /// after `remove`
+    #[serde(default)]
+    pub rename: IndexMap<String, String>,
+
+    /// Parameters forced to a value, replacing any incoming value; appended
+    /// in the order written
+    #[serde(default)]
+    pub set: IndexMap<String, String>,
+
+    /// Parameters added only when the client didn't send them, in the order
+    /// written
+    #[serde(default)]
+    pub add: IndexMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QueryPredicate {
+    pub name: String,
+
+    /// Required value; any value matches when unset
+    #[serde(default)]
+    pub value: Option<String>,
+
+    /// Require the parameter to be missing instead of present
+    #[serde(default)]
+    pub absent: bool,
+}
+
+impl QueryConfig {
+    pub fn has_rewrites(&self) -> bool {
+        !self.remove.is_empty()
+            || !self.rename.is_empty()
+            || !self.set.is_empty()
+            || !self.add.is_empty()
+    }
+}
@@ -0,0 +1,37 @@
+use ipnet::IpNet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RequestIdConfig {
+    #[serde(default = "default_header")]
+    pub header: String,
+
+    /// Reuse an incoming request ID from a trusted network instead of generating one
+    #[serde(default = "default_trust_incoming")]
+    pub trust_incoming: bool,
+
+    /// Peers whose incoming IDs are reused; none when empty
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub trusted_networks: Vec<IpNet>,
+}
+
+fn default_header() -> String {
+    "x-request-id".to_string()
+}
+
+fn default_trust_incoming() -> bool {
+    true
+}
+
+impl Default for RequestIdConfig {
+    fn default() -> Self {
+        Self {
+            header: default_header(),
+            trust_incoming: default_trust_incoming(),
+            trusted_networks: Vec::new(),
+        }
+    }
+}
@@ -1,14 +1,27 @@
-use crate::config::AuthConfig;
+use crate::config::{
+    AuthConfig, CorsConfig, ErrorPagesConfig, QueryConfig, RouteAction, RouteCacheConfig,
+    RouteCompressionConfig,
+};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct RouteConfig {
+    /// Request path pattern, interpreted according to `match_type`
     #[serde(default)]
     pub path: String,
 
+    /// Upstream URL for proxy routes; `{param}` placeholders are substituted
     #[serde(default)]
     pub target: String,
 
+    /// Name of an entry in `upstreams`; `target` is then empty or a path on it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<String>,
+
+    /// HTTP methods accepted by this route
     #[serde(default = "default_methods")]
     pub methods: Vec<String>,
 
@@ -17,15 +30,52 @@ pub struct RouteConfig {
 
     #[serde(default)]
     pub match_type: MatchType,
+
+    #[serde(default)]
+    pub query: QueryConfig,
+
+    #[serde(default)]
+    pub action: RouteAction,
+
+    #[serde(default)]
+    pub cache: RouteCacheConfig,
+
+    #[serde(default)]
+    pub compression: RouteCompressionConfig,
+
+    /// Replaces the global CORS policy for this route
+    #[serde(default)]
+    pub cors: Option<CorsConfig>,
+
+    /// Replaces the global error pages for this route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors: Option<ErrorPagesConfig>,
+
+    /// Names of the listeners serving this route; all of them when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listeners: Vec<String>,
+
+    /// File the route was loaded from, filled in by the loader
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    #[schemars(skip)]
+    pub source: Option<PathBuf>,
+
+    /// Target on the named upstream, filled in by `resolve_upstream`
+    #[serde(skip)]
+    pub resolved_target: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
 pub enum MatchType {
+    /// Exact path matching (default)
     #[default]
-    Exact, // Exact path matching (default)
-    Wildcard, // Wildcard matching with * and **
-    Regex,    // Regex pattern matching
-    Prefix,   // Prefix matching (starts with)
+    Exact,
+    /// Wildcard matching with * and **
+    Wildcard,
+    /// Regex pattern matching
+    Regex,
+    /// Prefix matching (starts with)
+    Prefix,
 }
 
 fn default_methods() -> Vec<String> {
@@ -37,9 +87,36 @@ impl Default for RouteConfig {
         Self {
             path: String::new(),
             target: String::new(),
+            upstream: None,
             methods: default_methods(),
             auth: AuthConfig::default(),
             match_type: MatchType::default(),
+            query: QueryConfig::default(),
+            action: RouteAction::default(),
+            cache: RouteCacheConfig::default(),
+            compression: RouteCompressionConfig::default(),
+            cors: None,
+            errors: None,
+            listeners: Vec::new(),
+            source: None,
+            resolved_target: None,
         }
     }
 }
+
+impl RouteConfig {
+    /// Whether requests arriving on the named listener may use this route
+    pub fn serves(&self, listener: &str) -> bool {
+        self.listeners.is_empty() || self.listeners.iter().any(|name| name == listener)
+    }
+
+    /// URL template requests are proxied to, on the upstream when one is named
+    pub fn upstream_target(&self) -> &str {
+        self.resolved_target.as_deref().unwrap_or(&self.target)
+    }
+
+    /// Upstream health and metrics are tracked per named upstream, or per target
+    pub fn upstream_key(&self) -> &str {
+        self.upstream.as_deref().unwrap_or(&self.target)
+    }
+}
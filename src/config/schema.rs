@@ -0,0 +1,46 @@
+use crate::config::AppConfig;
+use serde_json::Value;
+
+/// JSON Schema for the config file, for editors and pre-commit checks
+pub fn config_schema() -> Value {
+    let mut schema = schemars::schema_for!(AppConfig).to_value();
+    schema["title"] = Value::from("SAG configuration");
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_schema() {
+        let schema = config_schema();
+        let defs = &schema["$defs"];
+
+        assert_eq!(
+            schema["properties"]["server"]["$ref"],
+            "#/$defs/ServerConfig"
+        );
+        assert_eq!(defs["ServerConfig"]["properties"]["port"]["default"], 8080);
+        assert_eq!(
+            defs["LoggingConfig"]["properties"]["level"]["default"],
+            "info"
+        );
+
+        let auth_types = defs["AuthType"]["enum"].as_array().unwrap();
+        assert!(auth_types.contains(&Value::from("ApiKey")));
+        let match_types: Vec<&Value> = defs["MatchType"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| &variant["const"])
+            .collect();
+        assert!(match_types.contains(&&Value::from("Prefix")));
+
+        assert_eq!(
+            defs["RouteConfig"]["properties"]["methods"]["description"],
+            "HTTP methods accepted by this route"
+        );
+        assert_eq!(defs["RouteConfig"]["additionalProperties"], false);
+    }
+}
@@ -1,7 +1,10 @@
+use crate::config::{ListenAddress, ListenerConfig};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ServerConfig {
     #[serde(default = "default_host")]
     pub host: IpAddr,
@@ -9,8 +12,21 @@ pub struct ServerConfig {
     #[serde(default = "default_port")]
     pub port: u16,
 
+    /// Addresses to serve on; `host` and `port` are only used when empty
+    #[serde(default)]
+    pub listeners: Vec<ListenerConfig>,
+
     #[serde(default = "default_max_connections")]
     pub max_connections: usize,
+
+    /// Keep accepting after a shutdown signal, while readiness already fails,
+    /// so load balancers can take the instance out of rotation
+    #[serde(default)]
+    pub shutdown_delay_secs: u64,
+
+    /// How long in-flight requests may take to finish before exiting anyway
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
 }
 
 fn default_host() -> IpAddr {
@@ -25,12 +41,33 @@ fn default_max_connections() -> usize {
     1024
 }
 
+fn default_drain_timeout_secs() -> u64 {
+    30
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             host: default_host(),
             port: default_port(),
+            listeners: Vec::new(),
             max_connections: default_max_connections(),
+            shutdown_delay_secs: 0,
+            drain_timeout_secs: default_drain_timeout_secs(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Configured listeners, or a single one named "default" on `host`:`port`
+    pub fn effective_listeners(&self) -> Vec<ListenerConfig> {
+        if !self.listeners.is_empty() {
+            return self.listeners.clone();
         }
+        vec![ListenerConfig {
+            name: Some("default".to_string()),
+            address: ListenAddress::Tcp(SocketAddr::new(self.host, self.port)),
+            tls: None,
+        }]
     }
 }
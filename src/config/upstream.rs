@@ -0,0 +1,162 @@
+use crate::config::RouteConfig;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf};
+
+/// A backend defined once and referenced by name from routes
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamConfig {
+    /// Base URL of the backend
+    pub url: String,
+
+    /// Limit for the whole upstream call, in seconds
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+
+    /// Limit for establishing a connection, in seconds
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+
+    /// Idle keep-alive connections kept open per host
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+
+    /// Certificate checks for an https URL
+    #[serde(default)]
+    pub tls: Option<UpstreamTlsConfig>,
+
+    /// Probe the backend periodically instead of only watching proxied requests
+    #[serde(default)]
+    pub health_check: Option<HealthCheckConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamTlsConfig {
+    /// PEM CA certificates trusted in addition to the system roots
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Accept any certificate; only for testing against self-signed backends
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HealthCheckConfig {
+    /// Path requested with GET on the upstream URL; any 2xx or 3xx is healthy
+    #[serde(default = "default_health_path")]
+    pub path: String,
+
+    /// Time between probes, in seconds
+    #[serde(default = "default_health_interval")]
+    pub interval_secs: u64,
+
+    /// Limit for a single probe, in seconds
+    #[serde(default = "default_health_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_health_path() -> String {
+    "/health".to_string()
+}
+
+fn default_health_interval() -> u64 {
+    10
+}
+
+fn default_health_timeout() -> u64 {
+    2
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            path: default_health_path(),
+            interval_secs: default_health_interval(),
+            timeout_secs: default_health_timeout(),
+        }
+    }
+}
+
+/// Point a route that names an upstream at the upstream's URL. An empty
+/// target stands for the URL and a path target is appended to it; `target`
+/// itself is kept as written.
+pub fn resolve_upstream(
+    route: &mut RouteConfig,
+    upstreams: &HashMap<String, UpstreamConfig>,
+) -> Result<(), String> {
+    let Some(name) = &route.upstream else {
+        route.resolved_target = None;
+        return Ok(());
+    };
+    let upstream = upstreams.get(name).ok_or_else(|| {
+        format!(
+            "Route {:?} references unknown upstream {:?}",
+            route.path, name
+        )
+    })?;
+
+    let resolved = if route.target.is_empty() {
+        upstream.url.clone()
+    } else if route.target.starts_with('/') {
+        format!("{}{}", upstream.url.trim_end_matches('/'), route.target)
+    } else {
+        return Err(format!(
+            "Route {:?} uses upstream {:?}, its target must be empty or a path",
+            route.path, name
+        ));
+    };
+    route.resolved_target = Some(resolved);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_upstream() {
+        let upstreams = HashMap::from([(
+            "users".to_string(),
+            UpstreamConfig {
+                url: "http://users:8080/".to_string(),
+                timeout_secs: None,
+                connect_timeout_secs: None,
+                pool_max_idle_per_host: None,
+                tls: None,
+                health_check: None,
+            },
+        )]);
+        let mut route = RouteConfig {
+            path: "/users/{id}".to_string(),
+            target: "/v2/users/{id}".to_string(),
+            upstream: Some("users".to_string()),
+            ..Default::default()
+        };
+
+        resolve_upstream(&mut route, &upstreams).unwrap();
+        assert_eq!(route.upstream_target(), "http://users:8080/v2/users/{id}");
+        // Resolving twice changes nothing
+        resolve_upstream(&mut route, &upstreams).unwrap();
+        assert_eq!(route.upstream_target(), "http://users:8080/v2/users/{id}");
+        assert_eq!(route.target, "/v2/users/{id}");
+
+        route.target = String::new();
+        resolve_upstream(&mut route, &upstreams).unwrap();
+        assert_eq!(route.upstream_target(), "http://users:8080/");
+
+        route.target = "users".to_string();
+        assert!(resolve_upstream(&mut route, &upstreams).is_err());
+
+        // An absolute target would bypass the upstream's address
+        route.target = "http://other".to_string();
+        assert!(resolve_upstream(&mut route, &upstreams).is_err());
+
+        route.target = String::new();
+        route.upstream = Some("orders".to_string());
+        assert!(resolve_upstream(&mut route, &upstreams).is_err());
+    }
+}
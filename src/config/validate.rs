@@ -0,0 +1,961 @@
+use crate::config::{
+    AppConfig, CorsConfig, ErrorPagesConfig, MatchType, QueryConfig, RouteAction, RouteConfig,
+    RouteGroup,
+    interpolate::{Scope, env_var, interpolate_yaml},
+    load_config,
+    loader::{included_files, source_name},
+};
+use crate::server::matcher::RouteMatcher;
+use regex::Regex;
+use serde_yaml::Value;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single validation finding, located in the config file when possible
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub severity: Severity,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    fn error(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            file: None,
+            line,
+            message: message.into(),
+        }
+    }
+
+    fn warning(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            file: None,
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => {
+                write!(
+                    f,
+                    "{}: {}:{}: {}",
+                    severity,
+                    file.display(),
+                    line,
+                    self.message
+                )
+            }
+            (Some(file), None) => write!(f, "{}: {}: {}", severity, file.display(), self.message),
+            (None, Some(line)) => write!(f, "{}: line {}: {}", severity, line, self.message),
+            (None, None) => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+pub fn has_errors(problems: &[Problem]) -> bool {
+    problems.iter().any(|p| p.severity == Severity::Error)
+}
+
+/// Load the config the way the server does and collect every problem.
+/// The config is only returned when there are no errors.
+pub fn check(path_opt: Option<&Path>) -> (Option<AppConfig>, Vec<Problem>) {
+    let file = match path_opt {
+        Some(path) => Some(path.to_path_buf()),
+        None => Some(PathBuf::from("config.yaml")).filter(|p| p.exists()),
+    };
+
+    // The files on their own give problems with line numbers
+    let mut problems = file.as_deref().map(validate_file).unwrap_or_default();
+    for included in file.as_deref().map(includes).unwrap_or_default() {
+        problems.extend(validate_file(&included));
+    }
+    if has_errors(&problems) {
+        return (None, problems);
+    }
+
+    let config = match load_config(path_opt) {
+        Ok(config) => config,
+        Err(e) => {
+            problems.push(Problem::error(None, format!("{:#}", e)));
+            return (None, problems);
+        }
+    };
+
+    // The file's own problems are already reported with their lines, but
+    // environment overrides may still introduce errors
+    problems.extend(
+        validate_config(&config)
+            .into_iter()
+            .filter(|problem| file.is_none() || problem.severity == Severity::Error),
+    );
+
+    problems.extend(check_load_order(&config.routes));
+
+    if has_errors(&problems) {
+        (None, problems)
+    } else {
+        (Some(config), problems)
+    }
+}
+
+/// Check a config file, reporting every problem found in one pass
+pub fn validate_file(path: &Path) -> Vec<Problem> {
+    let problems = match std::fs::read_to_string(path) {
+        Ok(source) => validate_source(&source),
+        Err(e) => vec![Problem::error(None, format!("Failed to read file: {}", e))],
+    };
+
+    problems
+        .into_iter()
+        .map(|problem| Problem {
+            file: Some(path.to_path_buf()),
+            ..problem
+        })
+        .collect()
+}
+
+// Route files named by the `include` patterns; bad patterns surface when loading
+fn includes(path: &Path) -> Vec<PathBuf> {
+    let patterns = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|source| serde_yaml::from_str::<Value>(&source).ok())
+        .and_then(|document| {
+            serde_yaml::from_value::<Vec<String>>(document.get("include")?.clone()).ok()
+        })
+        .unwrap_or_default();
+    included_files(path, &patterns).unwrap_or_default()
+}
+
+pub fn validate_source(source: &str) -> Vec<Problem> {
+    let mut document: Value = match serde_yaml::from_str(source) {
+        Ok(document) => document,
+        Err(e) => {
+            return vec![Problem::error(
+                e.location().map(|l| l.line()),
+                e.to_string(),
+            )];
+        }
+    };
+
+    let mut problems = Vec::new();
+    let lines = item_lines(source, "routes");
+
+    // Checks below see the values the server will use
+    for e in interpolate_yaml(&mut document, Scope::All, &env_var) {
+        let line = source
+            .lines()
+            .position(|line| line.contains(&format!("${{{}", e.reference)))
+            .map(|i| i + 1);
+        problems.push(Problem::error(line, e.to_string()));
+    }
+
+    // Deserialize each route on its own so one typo doesn't hide the rest
+    let items = match document.get("routes") {
+        Some(Value::Sequence(items)) => items.clone(),
+        _ => Vec::new(),
+    };
+    let mut routes = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        match serde_yaml::from_value::<RouteConfig>(item.clone()) {
+            Ok(route) => routes.push((i, route)),
+            Err(e) => problems.push(Problem::error(
+                lines.get(i).copied(),
+                format!("routes[{}]: {}", i, e),
+            )),
+        }
+    }
+    let routes_ok = routes.len() == items.len();
+
+    // Interpolation only changes strings, so the raw source locates type errors
+    match serde_yaml::from_str::<AppConfig>(source) {
+        Ok(_) => match serde_yaml::from_value::<AppConfig>(document.clone()) {
+            Ok(config) => problems.extend(validate_settings(&config)),
+            Err(e) => problems.push(Problem::error(None, e.to_string())),
+        },
+        Err(e) => {
+            // Route errors were reported above; anything else is outside the routes
+            let mut rest = document.clone();
+            if let Value::Mapping(map) = &mut rest {
+                map.remove("routes");
+            }
+            match serde_yaml::from_value::<AppConfig>(rest) {
+                Ok(config) => problems.extend(validate_settings(&config)),
+                Err(_) if routes_ok => problems.push(Problem::error(
+                    e.location().map(|l| l.line()),
+                    e.to_string(),
+                )),
+                Err(e) => problems.push(Problem::error(None, e.to_string())),
+            }
+        }
+    }
+
+    let routes: Vec<(usize, &RouteConfig)> = routes.iter().map(|(i, r)| (*i, r)).collect();
+    problems.extend(validate_routes(&routes).into_iter().map(|problem| {
+        match route_index(&problem) {
+            Some(i) => Problem {
+                line: lines.get(i).copied(),
+                ..problem
+            },
+            None => problem,
+        }
+    }));
+
+    // Group members are checked per group and located at the group
+    let group_lines = item_lines(source, "groups");
+    let groups = document
+        .get("groups")
+        .and_then(|groups| serde_yaml::from_value::<Vec<RouteGroup>>(groups.clone()).ok());
+    for (g, group) in groups.iter().flatten().enumerate() {
+        let found = match group.flatten() {
+            Ok(members) => {
+                let members: Vec<(usize, &RouteConfig)> = members.iter().enumerate().collect();
+                let mut found = validate_routes(&members);
+                // Members are added after every top-level route of the file
+                for (m, member) in &members {
+                    let hidden = routes
+                        .iter()
+                        .find_map(|(j, earlier)| check_overlap(*j, earlier, *m, member));
+                    found.extend(hidden);
+                }
+                found
+            }
+            Err(e) => vec![Problem::error(None, e)],
+        };
+        problems.extend(found.into_iter().map(|problem| Problem {
+            line: group_lines.get(g).copied(),
+            message: format!("groups[{}].{}", g, problem.message),
+            ..problem
+        }));
+    }
+
+    // Report in file order, unlocated problems last
+    problems.sort_by_key(|problem| problem.line.unwrap_or(usize::MAX));
+    problems
+}
+
+/// Semantic checks on a fully deserialized config
+pub fn validate_config(config: &AppConfig) -> Vec<Problem> {
+    let routes: Vec<(usize, &RouteConfig)> = config.routes.iter().enumerate().collect();
+    let mut problems: Vec<Problem> = validate_routes(&routes)
+        .into_iter()
+        .map(|problem| Problem {
+            file: route_index(&problem).and_then(|i| config.routes.get(i)?.source.clone()),
+            ..problem
+        })
+        .collect();
+    problems.extend(validate_settings(config));
+    problems
+}
+
+/// Checks for routes replacing those of a running server with these listeners
+pub fn validate_route_list(listeners: &[String], routes: &[RouteConfig]) -> Vec<Problem> {
+    let indexed: Vec<(usize, &RouteConfig)> = routes.iter().enumerate().collect();
+    let mut problems = validate_routes(&indexed);
+    problems.extend(check_route_listeners(listeners, routes));
+    problems
+}
+
+// Routes are paired with their position in the config file
+fn validate_routes(routes: &[(usize, &RouteConfig)]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for (position, (i, route)) in routes.iter().enumerate() {
+        check_route(*i, route, &mut problems);
+
+        for (j, earlier) in &routes[..position] {
+            if let Some(problem) = check_overlap(*j, earlier, *i, route) {
+                problems.push(problem);
+                break;
+            }
+        }
+    }
+
+    problems
+}
+
+fn validate_settings(config: &AppConfig) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    if config.admin.enabled && config.admin.token.as_deref().is_none_or(str::is_empty) {
+        problems.push(Problem::error(
+            None,
+            "admin: a token is required when the admin API is enabled",
+        ));
+    }
+    let mut upstreams: Vec<_> = config.upstreams.iter().collect();
+    upstreams.sort_by_key(|(name, _)| *name);
+    for (name, upstream) in upstreams {
+        if let Err(e) = check_target(&upstream.url) {
+            problems.push(Problem::error(
+                None,
+                format!("upstreams.{}: invalid url {:?}: {}", name, upstream.url, e),
+            ));
+        }
+        if let Some(ca_cert) = upstream.tls.as_ref().and_then(|tls| tls.ca_cert.as_ref())
+            && !ca_cert.is_file()
+        {
+            problems.push(Problem::error(
+                None,
+                format!(
+                    "upstreams.{}: CA certificate {} does not exist",
+                    name,
+                    ca_cert.display()
+                ),
+            ));
+        }
+        if upstream
+            .health_check
+            .as_ref()
+            .is_some_and(|check| check.interval_secs == 0)
+        {
+            problems.push(Problem::error(
+                None,
+                format!(
+                    "upstreams.{}: health_check.interval_secs must be positive",
+                    name
+                ),
+            ));
+        }
+    }
+    for (i, route) in config.routes.iter().enumerate() {
+        if let Some(name) = &route.upstream
+            && !config.upstreams.contains_key(name)
+        {
+            problems.push(Problem::error(
+                None,
+                format!("routes[{}]: unknown upstream {:?}", i, name),
+            ));
+        }
+    }
+    for message in check_error_pages(&config.errors) {
+        problems.push(Problem::error(None, format!("errors.{}", message)));
+    }
+    for message in check_cors(&config.cors) {
+        problems.push(Problem::error(None, format!("cors.{}", message)));
+    }
+    problems.extend(check_listeners(config));
+    if config.health.enabled && config.health.on_admin && !config.admin.enabled {
+        problems.push(Problem::error(
+            None,
+            "health: on_admin requires the admin API to be enabled",
+        ));
+    }
+    if config.metrics.enabled
+        && config.metrics.listen.is_none()
+        && !config.metrics.public
+        && !config.admin.enabled
+    {
+        problems.push(Problem::error(
+            None,
+            "metrics: set listen or public, or enable the admin API to serve them there",
+        ));
+    }
+
+    problems
+}
+
+fn check_listeners(config: &AppConfig) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let listeners = config.server.effective_listeners();
+
+    for (i, listener) in listeners.iter().enumerate() {
+        let earlier = &listeners[..i];
+        if earlier.iter().any(|other| other.name() == listener.name()) {
+            problems.push(Problem::error(
+                None,
+                format!(
+                    "server.listeners[{}]: name {:?} is used twice",
+                    i,
+                    listener.name()
+                ),
+            ));
+        }
+        if earlier
+            .iter()
+            .any(|other| other.address == listener.address)
+        {
+            problems.push(Problem::error(
+                None,
+                format!(
+                    "server.listeners[{}]: address {} is used twice",
+                    i, listener.address
+                ),
+            ));
+        }
+        if let Some(tls) = &listener.tls {
+            for file in [&tls.cert, &tls.key] {
+                if !file.is_file() {
+                    problems.push(Problem::error(
+                        None,
+                        format!(
+                            "server.listeners[{}]: TLS file {} does not exist",
+                            i,
+                            file.display()
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    let names: Vec<String> = listeners.iter().map(|listener| listener.name()).collect();
+    problems.extend(check_route_listeners(&names, &config.routes));
+    problems
+}
+
+fn check_route_listeners(listeners: &[String], routes: &[RouteConfig]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (i, route) in routes.iter().enumerate() {
+        for name in &route.listeners {
+            if !listeners.contains(name) {
+                problems.push(Problem::error(
+                    None,
+                    format!("routes[{}]: unknown listener {:?}", i, name),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+fn check_route(i: usize, route: &RouteConfig, problems: &mut Vec<Problem>) {
+    // Targets on a named upstream are paths, resolved when the config loads
+    if let Some(upstream) = &route.upstream {
+        if !route.target.is_empty() && !route.target.starts_with('/') {
+            problems.push(Problem::error(
+                None,
+                format!(
+                    "routes[{}]: target {:?} must be empty or a path, it is on upstream {:?}",
+                    i, route.target, upstream
+                ),
+            ));
+        }
+    } else if matches!(route.action, RouteAction::Proxy) {
+        if route.target.is_empty() {
+            problems.push(Problem::error(
+                None,
+                format!("routes[{}]: proxy route {:?} has no target", i, route.path),
+            ));
+        } else if let Err(e) = check_target(&route.target) {
+            problems.push(Problem::error(
+                None,
+                format!("routes[{}]: invalid target {:?}: {}", i, route.target, e),
+            ));
+        }
+    }
+
+    if let Err(e) = RouteMatcher::new(vec![route.clone()]) {
+        problems.push(Problem::error(
+            None,
+            format!("routes[{}]: invalid pattern {:?}: {}", i, route.path, e),
+        ));
+    }
+
+    for message in route.cors.iter().flat_map(check_cors) {
+        problems.push(Problem::error(
+            None,
+            format!("routes[{}].cors.{}", i, message),
+        ));
+    }
+
+    for message in route.errors.iter().flat_map(check_error_pages) {
+        problems.push(Problem::error(
+            None,
+            format!("routes[{}].errors.{}", i, message),
+        ));
+    }
+
+    for message in check_query(&route.query) {
+        problems.push(Problem::error(
+            None,
+            format!("routes[{}].query.{}", i, message),
+        ));
+    }
+}
+
+// Removal runs before renaming, so a rename touching a removed name
+// would silently undo or ignore the removal
+fn check_query(config: &QueryConfig) -> Vec<String> {
+    config
+        .rename
+        .iter()
+        .filter(|(from, to)| config.remove.contains(from) || config.remove.contains(to))
+        .map(|(from, to)| {
+            format!(
+                "rename.{}: renaming to {:?} conflicts with remove",
+                from, to
+            )
+        })
+        .collect()
+}
+
+fn check_cors(config: &CorsConfig) -> Vec<String> {
+    // Credentials would otherwise be shared with every site
+    if config.allow_credentials && config.allowed_origins.iter().any(|o| o == "*") {
+        vec!["allowed_origins: \"*\" cannot be combined with allow_credentials".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+fn check_error_pages(config: &ErrorPagesConfig) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut pages: Vec<_> = config.pages.iter().collect();
+    pages.sort_by_key(|(key, _)| *key);
+    for (key, page) in pages {
+        let valid_key = match key.strip_suffix("xx") {
+            Some(class) => matches!(class, "4" | "5"),
+            None => key
+                .parse::<u16>()
+                .is_ok_and(|code| (400..600).contains(&code)),
+        };
+        if !valid_key {
+            messages.push(format!(
+                "pages.{}: expected an error status like \"404\" or \"5xx\"",
+                key
+            ));
+        }
+        match (&page.body, &page.file) {
+            (None, None) => messages.push(format!("pages.{}: needs a body or a file", key)),
+            (None, Some(file)) if !file.is_file() => messages.push(format!(
+                "pages.{}: file {} does not exist",
+                key,
+                file.display()
+            )),
+            _ => {}
+        }
+    }
+    messages
+}
+
+fn check_target(target: &str) -> Result<(), String> {
+    // Placeholders are filled per request; any host-safe stand-in will do
+    let placeholder = Regex::new(r"\{[^}]*\}").expect("valid placeholder regex");
+    let url = url::Url::parse(&placeholder.replace_all(target, "x")).map_err(|e| e.to_string())?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("unsupported scheme {:?}", url.scheme()));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err("missing host".to_string());
+    }
+    Ok(())
+}
+
+// Included files are loaded after the main file, so a route there can be
+// hidden by one from another file without either file showing a problem
+fn check_load_order(routes: &[RouteConfig]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (i, route) in routes.iter().enumerate() {
+        let hidden = routes[..i]
+            .iter()
+            .enumerate()
+            .filter(|(_, earlier)| earlier.source != route.source)
+            .find_map(|(j, earlier)| Some((earlier, check_overlap(j, earlier, i, route)?)));
+        if let Some((earlier, problem)) = hidden {
+            problems.push(Problem {
+                file: route.source.clone(),
+                message: format!(
+                    "route {:?} is unreachable, {:?} {:?} in {} matches first",
+                    route.path,
+                    earlier.match_type,
+                    earlier.path,
+                    source_name(earlier)
+                ),
+                ..problem
+            });
+        }
+    }
+    problems
+}
+
+// Report when an earlier route makes a later one unreachable
+fn check_overlap(
+    j: usize,
+    earlier: &RouteConfig,
+    i: usize,
+    route: &RouteConfig,
+) -> Option<Problem> {
+    // Query predicates can let requests fall through to later routes, methods
+    // are only checked once a route has matched
+    if !earlier.query.predicates.is_empty() {
+        return None;
+    }
+    // A route limited to some listeners only shadows routes limited to those
+    let listeners_cover = earlier.listeners.is_empty()
+        || (!route.listeners.is_empty()
+            && route
+                .listeners
+                .iter()
+                .all(|name| earlier.listeners.contains(name)));
+    if !listeners_cover {
+        return None;
+    }
+
+    let same_type =
+        std::mem::discriminant(&earlier.match_type) == std::mem::discriminant(&route.match_type);
+    if same_type && earlier.path == route.path {
+        let message = format!(
+            "routes[{}]: duplicate of routes[{}] ({:?} {:?})",
+            i, j, route.match_type, route.path
+        );
+        return Some(Problem::error(None, message));
+    }
+
+    let shadowed = match route.match_type {
+        MatchType::Exact => RouteMatcher::new(vec![earlier.clone()])
+            .is_ok_and(|matcher| matcher.find_match(exact_path(&route.path), None).is_some()),
+        MatchType::Prefix => {
+            matches!(earlier.match_type, MatchType::Prefix) && route.path.starts_with(&earlier.path)
+        }
+        MatchType::Wildcard | MatchType::Regex => false,
+    };
+
+    shadowed.then(|| {
+        Problem::warning(
+            None,
+            format!(
+                "routes[{}]: {:?} is unreachable, routes[{}] ({:?} {:?}) matches first",
+                i, route.path, j, earlier.match_type, earlier.path
+            ),
+        )
+    })
+}
+
+// An empty exact path matches the root
+fn exact_path(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
+
+fn route_index(problem: &Problem) -> Option<usize> {
+    let rest = problem.message.strip_prefix("routes[")?;
+    rest[..rest.find(']')?].parse().ok()
+}
+
+// Line numbers of the block-style items of a top-level list, in order
+fn item_lines(source: &str, key: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut in_list = false;
+    let mut item_indent = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let is_item = trimmed == "-" || trimmed.starts_with("- ");
+
+        if indent == 0 && !is_item {
+            in_list = trimmed
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.starts_with(':'));
+            item_indent = None;
+            continue;
+        }
+
+        if in_list && is_item && item_indent.is_none_or(|i| i == indent) {
+            item_indent = Some(indent);
+            lines.push(number + 1);
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+server:
+  port: 8080
+
+routes:
+  - path: /api
+    target: http://api
+    match_type: Prefix
+  - path: /api/users
+    target: http://users
+  - path: /files/{name
+    target: ftp://files
+    match_type: Regex
+  - path: /typo
+    target: http://typo
+    matchtype: Prefix
+  - path: /api
+    target: ''
+    match_type: Prefix
+";
+
+    fn render(problems: &[Problem]) -> Vec<String> {
+        problems.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_reports_all_problems() {
+        let messages = render(&validate_source(CONFIG));
+
+        assert_eq!(messages.len(), 6, "{:#?}", messages);
+        assert!(
+            messages[0].starts_with("warning: line 8: routes[1]: \"/api/users\" is unreachable")
+        );
+        assert!(
+            messages[1].starts_with("error: line 10: routes[2]: invalid target \"ftp://files\"")
+        );
+        assert!(messages[2].starts_with("error: line 10: routes[2]: invalid pattern"));
+        assert!(messages[3].starts_with("error: line 13: routes[3]: unknown field `matchtype`"));
+        assert!(
+            messages[4]
+                .starts_with("error: line 16: routes[4]: proxy route \"/api\" has no target")
+        );
+        assert!(messages[5].starts_with("error: line 16: routes[4]: duplicate of routes[0]"));
+    }
+
+    #[test]
+    fn test_unknown_top_level_field() {
+        let problems = validate_source("server:\n  port: 8080\n  hots: 0.0.0.0\n");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(3));
+        assert!(problems[0].message.contains("unknown field `hots`"));
+    }
+
+    #[test]
+    fn test_error_pages() {
+        let source = "\
+errors:
+  pages:
+    \"200\": { body: ok }
+    5xx: {}
+";
+        let problems = validate_source(source);
+        assert_eq!(problems.len(), 2, "{:#?}", problems);
+        assert!(
+            problems[0]
+                .message
+                .starts_with("errors.pages.200: expected an error status")
+        );
+        assert_eq!(
+            problems[1].message,
+            "errors.pages.5xx: needs a body or a file"
+        );
+    }
+
+    #[test]
+    fn test_cors_credentials() {
+        let source = "\
+cors:
+  allowed_origins: [\"*\"]
+  allow_credentials: true
+routes:
+  - path: /a
+    target: http://a
+    cors:
+      allowed_origins: [\"https://*.example.com\"]
+      allow_credentials: true
+";
+        let messages = render(&validate_source(source));
+        assert_eq!(
+            messages,
+            vec!["error: cors.allowed_origins: \"*\" cannot be combined with allow_credentials"]
+        );
+    }
+
+    #[test]
+    fn test_query_rename_and_remove() {
+        let source = "\
+routes:
+  - path: /a
+    target: http://a
+    query:
+      remove: [token, q]
+      rename: { query: q, page: p }
+";
+        let messages = render(&validate_source(source));
+        assert_eq!(
+            messages,
+            vec![
+                "error: line 2: routes[0].query.rename.query: renaming to \"q\" conflicts with remove"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_upstream_targets() {
+        let source = "\
+upstreams:
+  users: { url: http://users.internal }
+routes:
+  - path: /users
+    upstream: users
+    target: /v2/users
+  - path: /accounts
+    upstream: users
+    target: http://accounts.internal
+  - path: /orders
+    upstream: orders
+";
+        let messages = render(&validate_source(source));
+        assert_eq!(messages.len(), 2, "{:#?}", messages);
+        assert!(messages[0].starts_with(
+            "error: line 7: routes[1]: target \"http://accounts.internal\" must be empty or a path"
+        ));
+        assert_eq!(messages[1], "error: routes[2]: unknown upstream \"orders\"");
+    }
+
+    #[test]
+    fn test_listeners() {
+        let source = "\
+server:
+  listeners:
+    - name: public
+      address: 0.0.0.0:8443
+    - name: internal
+      address: 127.0.0.1:8080
+routes:
+  - path: /admin
+    target: http://admin
+    listeners: [internal]
+  - path: /admin
+    target: http://public
+    listeners: [public, sidecar]
+";
+        let problems = validate_source(source);
+        assert_eq!(problems.len(), 1, "{:#?}", problems);
+        assert!(problems[0].message.contains("unknown listener \"sidecar\""));
+    }
+
+    #[test]
+    fn test_unresolved_reference() {
+        let source = "routes:\n  - path: /a\n    target: ${SAG_VALIDATE_UNSET}\n";
+        let problems = validate_source(source);
+        assert_eq!(problems.len(), 2, "{:#?}", problems);
+        // The target is still checked as written
+        assert!(problems[0].message.contains("invalid target"));
+        assert_eq!(problems[1].line, Some(3));
+        assert!(
+            problems[1]
+                .message
+                .contains("environment variable is not set")
+        );
+    }
+
+    #[test]
+    fn test_group_members() {
+        let source = "\
+groups:
+  - prefix: /api
+    defaults:
+      target: http://api
+    routes:
+      - path: /users
+      - path: /orders
+        target: api
+";
+        let problems = validate_source(source);
+        assert_eq!(problems.len(), 1, "{:#?}", problems);
+        assert_eq!(problems[0].line, Some(2));
+        assert!(
+            problems[0]
+                .message
+                .starts_with("groups[0].routes[1]: invalid target \"api\"")
+        );
+    }
+
+    #[test]
+    fn test_check_target() {
+        assert!(check_target("http://users/{id}").is_ok());
+        assert!(check_target("https://{tenant}.example.com").is_ok());
+        assert!(check_target("users.internal").is_err());
+        assert!(check_target("file:///etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_method_split_routes() {
+        // The first route matching the path wins, whatever the method
+        let source = "\
+routes:
+  - path: /users
+    target: http://reader
+    methods: [GET]
+  - path: /users
+    target: http://writer
+    methods: [POST]
+  - path: /
+    target: http://web
+    match_type: Prefix
+    methods: [GET]
+  - path: /admin
+    target: http://admin
+    methods: [DELETE]
+";
+        let messages = render(&validate_source(source));
+        assert_eq!(messages.len(), 2, "{:#?}", messages);
+        assert!(messages[0].starts_with("error: line 5: routes[1]: duplicate of routes[0]"));
+        assert!(messages[1].starts_with("warning: line 12: routes[3]: \"/admin\" is unreachable"));
+    }
+
+    #[test]
+    fn test_load_order() {
+        let dir = std::env::temp_dir().join(format!("sag-load-order-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("routes.d")).unwrap();
+        let main = dir.join("gateway.yaml");
+        std::fs::write(
+            &main,
+            "\
+include: [\"routes.d/*.yaml\"]
+routes:
+  - path: /
+    target: http://web
+    match_type: Prefix
+    methods: []
+groups:
+  - prefix: /api
+    defaults:
+      target: http://api
+    routes:
+      - path: /orders
+",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("routes.d/users.yaml"),
+            "routes:\n  - path: /users\n    target: http://users\n",
+        )
+        .unwrap();
+
+        let (config, problems) = check(Some(&main));
+        assert!(config.is_some(), "{:#?}", problems);
+        let messages = render(&problems);
+        assert_eq!(messages.len(), 2, "{:#?}", messages);
+        assert!(
+            messages[0]
+                .contains("gateway.yaml:8: groups[0].routes[0]: \"/api/orders\" is unreachable")
+        );
+        assert!(
+            messages[1].contains("users.yaml: route \"/users\" is unreachable, Prefix \"/\" in")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+use crate::logging::config::{AccessLogConfig, AccessLogFormat};
+use crate::logging::rotation::RotatingFile;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::io::{self, Write};
+use std::time::Duration;
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+
+const COMMON_TEMPLATE: &str =
+    "{client_ip} - - [{time_clf}] \"{method} {uri} {protocol}\" {status} {bytes}";
+const COMBINED_TEMPLATE: &str = "{client_ip} - - [{time_clf}] \"{method} {uri} {protocol}\" {status} {bytes} \"{referer}\" \"{user_agent}\"";
+
+/// One completed request, as recorded in the access log
+#[derive(Debug, Clone)]
+pub struct AccessRecord {
+    pub time: DateTime<Utc>,
+    pub client_ip: Option<String>,
+    pub request_id: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub protocol: String,
+    pub route: Option<String>,
+    pub target: Option<String>,
+    pub status: u16,
+    pub bytes: u64,
+    pub upstream_latency: Option<Duration>,
+    pub total_latency: Duration,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AccessRecord {
+    fn uri(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{}?{}", self.path, query),
+            None => self.path.clone(),
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let or_dash = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+
+        vec![
+            ("time", self.time.to_rfc3339()),
+            (
+                "time_clf",
+                self.time.format("%d/%b/%Y:%H:%M:%S %z").to_string(),
+            ),
+            ("client_ip", or_dash(&self.client_ip)),
+            ("request_id", or_dash(&self.request_id)),
+            ("method", self.method.clone()),
+            ("path", self.path.clone()),
+            ("query", or_dash(&self.query)),
+            ("uri", self.uri()),
+            ("protocol", self.protocol.clone()),
+            ("route", or_dash(&self.route)),
+            ("target", or_dash(&self.target)),
+            ("status", self.status.to_string()),
+            ("bytes", self.bytes.to_string()),
+            (
+                "upstream_ms",
+                self.upstream_latency
+                    .map(|d| format!("{:.3}", millis(d)))
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            ("total_ms", format!("{:.3}", millis(self.total_latency))),
+            ("referer", or_dash(&self.referer)),
+            ("user_agent", or_dash(&self.user_agent)),
+        ]
+    }
+
+    fn to_json(&self) -> String {
+        json!({
+            "time": self.time.to_rfc3339(),
+            "client_ip": self.client_ip,
+            "request_id": self.request_id,
+            "method": self.method,
+            "path": self.path,
+            "query": self.query,
+            "protocol": self.protocol,
+            "route": self.route,
+            "target": self.target,
+            "status": self.status,
+            "bytes": self.bytes,
+            "upstream_latency_ms": self.upstream_latency.map(millis),
+            "total_latency_ms": millis(self.total_latency),
+            "referer": self.referer,
+            "user_agent": self.user_agent,
+        })
+        .to_string()
+    }
+}
+
+/// Access log writer, independent of the diagnostic tracing subscriber
+pub struct AccessLogger {
+    writer: NonBlocking,
+    _guard: WorkerGuard,
+    json: bool,
+    template: String,
+}
+
+impl AccessLogger {
+    pub fn new(config: &AccessLogConfig) -> io::Result<Self> {
+        let (writer, guard) = match &config.path {
+            Some(path) => {
+                tracing_appender::non_blocking(RotatingFile::open(path, config.rotation.clone())?)
+            }
+            None => tracing_appender::non_blocking(io::stdout()),
+        };
+
+        let template = match config.format {
+            AccessLogFormat::Common => COMMON_TEMPLATE.to_string(),
+            AccessLogFormat::Custom => config
+                .template
+                .clone()
+                .unwrap_or_else(|| COMBINED_TEMPLATE.to_string()),
+            _ => COMBINED_TEMPLATE.to_string(),
+        };
+
+        Ok(Self {
+            writer,
+            _guard: guard,
+            json: matches!(config.format, AccessLogFormat::Json),
+            template,
+        })
+    }
+
+    pub fn format(&self, record: &AccessRecord) -> String {
+        if self.json {
+            record.to_json()
+        } else {
+            render_template(&self.template, record)
+        }
+    }
+
+    pub fn log(&self, record: &AccessRecord) {
+        let mut line = self.format(record);
+        line.push('\n');
+
+        if let Err(e) = self.writer.clone().write_all(line.as_bytes()) {
+            tracing::warn!("Failed to write access log: {}", e);
+        }
+    }
+}
+
+fn render_template(template: &str, record: &AccessRecord) -> String {
+    let mut line = template.to_string();
+    for (name, value) in record.fields() {
+        line = line.replace(&format!("{{{}}}", name), &value);
+    }
+    line
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> AccessRecord {
+        AccessRecord {
+            time: DateTime::parse_from_rfc3339("2025-06-01T13:45:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            client_ip: Some("10.0.0.1".to_string()),
+            request_id: Some("req-1".to_string()),
+            method: "GET".to_string(),
+            path: "/api/users".to_string(),
+            query: Some("page=2".to_string()),
+            protocol: "HTTP/1.1".to_string(),
+            route: Some("/api".to_string()),
+            target: Some("http://users.internal".to_string()),
+            status: 200,
+            bytes: 512,
+            upstream_latency: Some(Duration::from_millis(12)),
+            total_latency: Duration::from_millis(15),
+            referer: None,
+            user_agent: Some("curl/8.0".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_combined_format() {
+        let line = render_template(COMBINED_TEMPLATE, &record());
+        assert_eq!(
+            line,
+            "10.0.0.1 - - [01/Jun/2025:13:45:00 +0000] \"GET /api/users?page=2 HTTP/1.1\" 200 512 \"-\" \"curl/8.0\""
+        );
+    }
+
+    #[test]
+    fn test_custom_template() {
+        let line = render_template(
+            "{request_id} {route} -> {target} {status} {upstream_ms}ms/{total_ms}ms",
+            &record(),
+        );
+        assert_eq!(
+            line,
+            "req-1 /api -> http://users.internal 200 12.000ms/15.000ms"
+        );
+    }
+
+    #[test]
+    fn test_json_format() {
+        let value: serde_json::Value = serde_json::from_str(&record().to_json()).unwrap();
+        assert_eq!(value["status"], 200);
+        assert_eq!(value["route"], "/api");
+        assert_eq!(value["upstream_latency_ms"], 12.0);
+        assert!(value["referer"].is_null());
+    }
+}
@@ -1,7 +1,11 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct LoggingConfig {
+    /// Minimum level, e.g. "info" or "sag=debug,tower_http=warn"
     #[serde(default = "default_level")]
     pub level: String,
 
@@ -10,9 +14,19 @@ pub struct LoggingConfig {
 
     #[serde(default)]
     pub console: ConsoleConfig,
+
+    #[serde(default)]
+    pub file: FileLogConfig,
+
+    #[serde(default)]
+    pub access: AccessLogConfig,
+
+    #[serde(default)]
+    pub otel: OtelConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ConsoleConfig {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
@@ -21,7 +35,121 @@ pub struct ConsoleConfig {
     pub colors: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileLogConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_file_path")]
+    pub path: PathBuf,
+
+    #[serde(default = "default_file_format")]
+    pub format: LogFormat,
+
+    /// Falls back to the top-level level when unset
+    #[serde(default)]
+    pub level: Option<String>,
+
+    #[serde(default)]
+    pub rotation: RotationConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AccessLogConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub format: AccessLogFormat,
+
+    /// Used with the Custom format, e.g. "{client_ip} {method} {path} {status}"
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Written to stdout when unset
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    #[serde(default)]
+    pub rotation: RotationConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, JsonSchema)]
+pub enum AccessLogFormat {
+    Common,
+    #[default]
+    Combined,
+    Json,
+    Custom,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OtelConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub protocol: OtlpProtocol,
+
+    /// Defaults to the collector's standard port for the protocol
+    #[serde(default)]
+    pub endpoint: Option<String>,
+
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+
+    /// Fraction of new traces sampled; incoming sampling decisions are kept
+    #[serde(default = "default_sample_ratio")]
+    pub sample_ratio: f64,
+
+    #[serde(default = "default_propagators")]
+    pub propagators: Vec<Propagator>,
+
+    /// Falls back to the top-level level when unset
+    #[serde(default)]
+    pub level: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum OtlpProtocol {
+    #[default]
+    Grpc,
+    Http,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum Propagator {
+    TraceContext,
+    B3,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RotationConfig {
+    /// Rotate once the file grows beyond this many bytes
+    #[serde(default)]
+    pub max_size: Option<u64>,
+
+    #[serde(default)]
+    pub period: RotationPeriod,
+
+    /// Rotated files kept next to the active one
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum RotationPeriod {
+    #[default]
+    Never,
+    Hourly,
+    Daily,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, JsonSchema)]
 pub enum LogFormat {
     #[default]
     Pretty,
@@ -45,12 +173,75 @@ fn default_colors() -> bool {
     true
 }
 
+fn default_file_path() -> PathBuf {
+    PathBuf::from("logs/sag.log")
+}
+
+fn default_file_format() -> LogFormat {
+    LogFormat::Json
+}
+
+fn default_max_files() -> usize {
+    7
+}
+
+fn default_service_name() -> String {
+    "sag".to_string()
+}
+
+fn default_sample_ratio() -> f64 {
+    1.0
+}
+
+fn default_propagators() -> Vec<Propagator> {
+    vec![Propagator::TraceContext]
+}
+
 impl Default for LoggingConfig {
     fn default() -> Self {
         Self {
             level: default_level(),
             format: default_format(),
             console: ConsoleConfig::default(),
+            file: FileLogConfig::default(),
+            access: AccessLogConfig::default(),
+            otel: OtelConfig::default(),
+        }
+    }
+}
+
+impl Default for FileLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_file_path(),
+            format: default_file_format(),
+            level: None,
+            rotation: RotationConfig::default(),
+        }
+    }
+}
+
+impl Default for OtelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            protocol: OtlpProtocol::default(),
+            endpoint: None,
+            service_name: default_service_name(),
+            sample_ratio: default_sample_ratio(),
+            propagators: default_propagators(),
+            level: None,
+        }
+    }
+}
+
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            period: RotationPeriod::default(),
+            max_files: default_max_files(),
         }
     }
 }
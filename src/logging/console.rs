@@ -1,20 +1,18 @@
+use crate::logging::BoxedLayer;
 use crate::logging::config::{ConsoleConfig, LogFormat};
-use anyhow::Result;
 use tracing::Level;
 use tracing_subscriber::{
     EnvFilter, Layer,
     fmt::{self, format::FmtSpan},
-    layer::SubscriberExt,
-    util::SubscriberInitExt,
 };
 
-pub fn init_console_logging(
+pub fn console_layer(
     level: &str,
     format: &LogFormat,
     console_config: &ConsoleConfig,
-) -> Result<()> {
+) -> Option<BoxedLayer> {
     if !console_config.enabled {
-        return Ok(());
+        return None;
     }
 
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
@@ -40,9 +38,7 @@ pub fn init_console_logging(
             .boxed(),
     };
 
-    tracing_subscriber::registry().with(console_layer).init();
-
-    Ok(())
+    Some(console_layer)
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,70 @@
+use crate::logging::BoxedLayer;
+use crate::logging::config::{FileLogConfig, LogFormat};
+use crate::logging::rotation::RotatingFile;
+use anyhow::{Context, Result};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{EnvFilter, Layer, fmt};
+
+/// Build the file sink; the returned guard flushes pending lines when dropped
+pub fn file_layer(
+    default_level: &str,
+    file_config: &FileLogConfig,
+) -> Result<Option<(BoxedLayer, WorkerGuard)>> {
+    if !file_config.enabled {
+        return Ok(None);
+    }
+
+    let file = RotatingFile::open(&file_config.path, file_config.rotation.clone())
+        .with_context(|| format!("Failed to open log file {}", file_config.path.display()))?;
+    let (writer, guard) = tracing_appender::non_blocking(file);
+
+    let level = file_config.level.as_deref().unwrap_or(default_level);
+    let env_filter = EnvFilter::new(level);
+
+    let file_layer = match file_config.format {
+        LogFormat::Pretty => fmt::layer()
+            .pretty()
+            .with_ansi(false)
+            .with_writer(writer)
+            .with_filter(env_filter)
+            .boxed(),
+
+        LogFormat::Compact => fmt::layer()
+            .compact()
+            .with_ansi(false)
+            .with_writer(writer)
+            .with_filter(env_filter)
+            .boxed(),
+
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_ansi(false)
+            .with_writer(writer)
+            .with_filter(env_filter)
+            .boxed(),
+    };
+
+    Ok(Some((file_layer, guard)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_layer() {
+        let dir = std::env::temp_dir().join(format!("sag-file-log-{}", std::process::id()));
+        let mut config = FileLogConfig {
+            path: dir.join("sag.log"),
+            ..Default::default()
+        };
+
+        assert!(file_layer("info", &config).unwrap().is_none());
+
+        config.enabled = true;
+        assert!(file_layer("info", &config).unwrap().is_some());
+        assert!(config.path.exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
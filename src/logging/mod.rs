@@ -1,20 +1,81 @@
 pub mod config;
 #[allow(unused_imports)]
-pub use config::{ConsoleConfig, LogFormat, LoggingConfig};
+pub use config::{ConsoleConfig, FileLogConfig, LogFormat, LoggingConfig};
 
+pub mod access;
 pub mod console;
+pub mod file;
+pub mod otel;
+pub mod rotation;
 
 use anyhow::Result;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{Layer, Registry, layer::SubscriberExt, util::SubscriberInitExt};
+
+pub type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Keeps background log writers alive; pending lines and spans are flushed on drop
+pub struct LoggingGuard {
+    _file: Option<WorkerGuard>,
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.tracer_provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush trace spans: {}", e);
+        }
+    }
+}
 
 /// Initialize logging based on the provided configuration
-pub fn init_logging(logging_config: &LoggingConfig) -> Result<()> {
-    console::init_console_logging(
+pub fn init_logging(logging_config: &LoggingConfig) -> Result<LoggingGuard> {
+    let mut layers = Vec::new();
+
+    if let Some(layer) = console::console_layer(
         &logging_config.level,
         &logging_config.format,
         &logging_config.console,
-    )?;
+    ) {
+        layers.push(layer);
+    }
+
+    let file_guard = match file::file_layer(&logging_config.level, &logging_config.file)? {
+        Some((layer, guard)) => {
+            layers.push(layer);
+            Some(guard)
+        }
+        None => None,
+    };
+
+    let tracer_provider = match otel::otel_layer(&logging_config.level, &logging_config.otel)? {
+        Some((layer, provider)) => {
+            layers.push(layer);
+            Some(provider)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry().with(layers).init();
 
     tracing::info!("Logging initialized with level: {}", logging_config.level);
+    if logging_config.file.enabled {
+        tracing::info!("Logging to file: {}", logging_config.file.path.display());
+    }
+
+    if logging_config.otel.enabled {
+        tracing::info!(
+            "Exporting traces over OTLP/{:?} as {}",
+            logging_config.otel.protocol,
+            logging_config.otel.service_name
+        );
+    }
 
-    Ok(())
+    Ok(LoggingGuard {
+        _file: file_guard,
+        tracer_provider,
+    })
 }
@@ -0,0 +1,330 @@
+use crate::logging::BoxedLayer;
+use crate::logging::config::{OtelConfig, OtlpProtocol, Propagator};
+use anyhow::{Context as _, Result};
+use opentelemetry::{
+    Context, global,
+    propagation::{
+        Extractor, Injector, TextMapCompositePropagator, TextMapPropagator,
+        text_map_propagator::FieldIter,
+    },
+    trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider,
+    },
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource,
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+};
+use tracing_subscriber::{EnvFilter, Layer};
+
+const DEFAULT_GRPC_ENDPOINT: &str = "http://localhost:4317";
+const DEFAULT_HTTP_ENDPOINT: &str = "http://localhost:4318";
+const HTTP_TRACES_PATH: &str = "/v1/traces";
+
+/// Build the OTLP export layer; the provider flushes pending spans on shutdown
+pub fn otel_layer(
+    default_level: &str,
+    otel_config: &OtelConfig,
+) -> Result<Option<(BoxedLayer, SdkTracerProvider)>> {
+    if !otel_config.enabled {
+        return Ok(None);
+    }
+
+    let provider = tracer_provider(otel_config)?;
+    global::set_text_map_propagator(propagator(&otel_config.propagators));
+
+    let level = otel_config.level.as_deref().unwrap_or(default_level);
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("sag"))
+        .with_filter(EnvFilter::new(level))
+        .boxed();
+
+    Ok(Some((layer, provider)))
+}
+
+fn tracer_provider(otel_config: &OtelConfig) -> Result<SdkTracerProvider> {
+    let exporter = match otel_config.protocol {
+        OtlpProtocol::Grpc => SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(
+                otel_config
+                    .endpoint
+                    .as_deref()
+                    .unwrap_or(DEFAULT_GRPC_ENDPOINT),
+            )
+            .build(),
+        OtlpProtocol::Http => SpanExporter::builder()
+            .with_http()
+            .with_endpoint(http_endpoint(otel_config.endpoint.as_deref()))
+            .build(),
+    }
+    .context("Failed to build OTLP span exporter")?;
+
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+        otel_config.sample_ratio,
+    )));
+    let resource = Resource::builder()
+        .with_service_name(otel_config.service_name.clone())
+        .build();
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(sampler)
+        .with_resource(resource)
+        .build())
+}
+
+// The HTTP exporter uses the endpoint verbatim, so add the signal path
+// when only the collector base URL is configured
+fn http_endpoint(endpoint: Option<&str>) -> String {
+    let endpoint = endpoint.unwrap_or(DEFAULT_HTTP_ENDPOINT);
+    match endpoint.split_once("://") {
+        Some((_, rest)) if rest.trim_end_matches('/').contains('/') => endpoint.to_string(),
+        _ => format!("{}{}", endpoint.trim_end_matches('/'), HTTP_TRACES_PATH),
+    }
+}
+
+fn propagator(propagators: &[Propagator]) -> TextMapCompositePropagator {
+    let propagators = propagators
+        .iter()
+        .map(|propagator| -> Box<dyn TextMapPropagator + Send + Sync> {
+            match propagator {
+                Propagator::TraceContext => Box::new(TraceContextPropagator::new()),
+                Propagator::B3 => Box::new(B3Propagator::new()),
+            }
+        })
+        .collect();
+
+    TextMapCompositePropagator::new(propagators)
+}
+
+const B3_SINGLE: &str = "b3";
+const B3_TRACE_ID: &str = "x-b3-traceid";
+const B3_SPAN_ID: &str = "x-b3-spanid";
+const B3_SAMPLED: &str = "x-b3-sampled";
+const B3_FLAGS: &str = "x-b3-flags";
+
+/// Zipkin B3 propagation. Injects the single `b3` header and extracts
+/// either the single or the multi-header (`x-b3-*`) form.
+#[derive(Debug)]
+pub struct B3Propagator {
+    fields: Vec<String>,
+}
+
+impl B3Propagator {
+    pub fn new() -> Self {
+        Self {
+            fields: [B3_SINGLE, B3_TRACE_ID, B3_SPAN_ID, B3_SAMPLED, B3_FLAGS]
+                .iter()
+                .map(|field| field.to_string())
+                .collect(),
+        }
+    }
+
+    fn extract_single(&self, extractor: &dyn Extractor) -> Option<SpanContext> {
+        let value = extractor.get(B3_SINGLE)?;
+        let mut parts = value.trim().split('-');
+
+        let trace_id = parts.next()?;
+        // A lone sampling flag ("0", "1", "d") carries no context
+        let span_id = parts.next()?;
+        let sampled = parts.next().unwrap_or("1");
+
+        span_context(trace_id, span_id, matches!(sampled, "1" | "d"))
+    }
+
+    fn extract_multi(&self, extractor: &dyn Extractor) -> Option<SpanContext> {
+        let trace_id = extractor.get(B3_TRACE_ID)?;
+        let span_id = extractor.get(B3_SPAN_ID)?;
+        let debug = extractor.get(B3_FLAGS).is_some_and(|flags| flags == "1");
+        let sampled = extractor
+            .get(B3_SAMPLED)
+            .is_none_or(|sampled| sampled == "1" || sampled.eq_ignore_ascii_case("true"));
+
+        span_context(trace_id.trim(), span_id.trim(), debug || sampled)
+    }
+}
+
+impl Default for B3Propagator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextMapPropagator for B3Propagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        let span = cx.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return;
+        }
+
+        let sampled = if span_context.is_sampled() { "1" } else { "0" };
+        injector.set(
+            B3_SINGLE,
+            format!(
+                "{}-{}-{}",
+                span_context.trace_id(),
+                span_context.span_id(),
+                sampled
+            ),
+        );
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        match self
+            .extract_single(extractor)
+            .or_else(|| self.extract_multi(extractor))
+        {
+            Some(span_context) => cx.with_remote_span_context(span_context),
+            None => cx.clone(),
+        }
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(&self.fields)
+    }
+}
+
+fn span_context(trace_id: &str, span_id: &str, sampled: bool) -> Option<SpanContext> {
+    // 64-bit trace IDs are left-padded to 128 bits
+    if !matches!(trace_id.len(), 16 | 32) || span_id.len() != 16 {
+        return None;
+    }
+    let trace_id = TraceId::from_hex(trace_id).ok()?;
+    let span_id = SpanId::from_hex(span_id).ok()?;
+    let flags = if sampled {
+        TraceFlags::SAMPLED
+    } else {
+        TraceFlags::default()
+    };
+
+    let span_context = SpanContext::new(trace_id, span_id, flags, true, TraceState::default());
+    span_context.is_valid().then_some(span_context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, http::StatusCode, routing::post};
+    use opentelemetry::trace::{Span, Tracer};
+    use std::collections::HashMap;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn remote_context(propagator: &dyn TextMapPropagator, headers: &[(&str, &str)]) -> SpanContext {
+        let carrier: HashMap<String, String> = headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        propagator.extract(&carrier).span().span_context().clone()
+    }
+
+    #[test]
+    fn test_b3_extract() {
+        let b3 = B3Propagator::new();
+
+        let single = remote_context(
+            &b3,
+            &[("b3", "80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-1")],
+        );
+        assert!(single.is_remote());
+        assert!(single.is_sampled());
+        assert_eq!(
+            single.trace_id().to_string(),
+            "80f198ee56343ba864fe8b2a57d3eff7"
+        );
+        assert_eq!(single.span_id().to_string(), "e457b5a2e4d86bd1");
+
+        let multi = remote_context(
+            &b3,
+            &[
+                ("x-b3-traceid", "a3ce929d0e0e4736"),
+                ("x-b3-spanid", "00f067aa0ba902b7"),
+                ("x-b3-sampled", "0"),
+            ],
+        );
+        assert_eq!(
+            multi.trace_id().to_string(),
+            "0000000000000000a3ce929d0e0e4736"
+        );
+        assert!(!multi.is_sampled());
+
+        assert!(!remote_context(&b3, &[("b3", "1")]).is_valid());
+        assert!(!remote_context(&b3, &[("b3", "zz-e457b5a2e4d86bd1-1")]).is_valid());
+    }
+
+    #[test]
+    fn test_propagation_round_trip() {
+        let composite = propagator(&[Propagator::TraceContext, Propagator::B3]);
+        let mut carrier = HashMap::new();
+        carrier.insert("traceparent".to_string(), TRACEPARENT.to_string());
+        carrier.insert("tracestate".to_string(), "vendor=value".to_string());
+
+        let cx = composite.extract(&carrier);
+        assert_eq!(
+            cx.span().span_context().trace_state().header(),
+            "vendor=value"
+        );
+
+        let mut injected: HashMap<String, String> = HashMap::new();
+        composite.inject_context(&cx, &mut injected);
+        assert_eq!(injected["traceparent"], TRACEPARENT);
+        assert_eq!(injected["tracestate"], "vendor=value");
+        assert_eq!(
+            injected["b3"],
+            "4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-1"
+        );
+    }
+
+    #[test]
+    fn test_http_endpoint() {
+        assert_eq!(http_endpoint(None), "http://localhost:4318/v1/traces");
+        assert_eq!(
+            http_endpoint(Some("http://collector:4318/")),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            http_endpoint(Some("http://collector/otlp/v1/traces")),
+            "http://collector/otlp/v1/traces"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_exports_to_collector() {
+        // Minimal OTLP/HTTP collector that only counts export calls
+        let exports = Arc::new(AtomicUsize::new(0));
+        let counter = exports.clone();
+        let collector = Router::new().route(
+            "/v1/traces",
+            post(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                StatusCode::OK
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, collector).await });
+
+        let config = OtelConfig {
+            enabled: true,
+            protocol: OtlpProtocol::Http,
+            endpoint: Some(format!("http://{}", addr)),
+            ..Default::default()
+        };
+        let provider = tracer_provider(&config).unwrap();
+        provider.tracer("test").start("request").end();
+
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(exports.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::logging::config::{RotationConfig, RotationPeriod};
+use chrono::{DateTime, Utc};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Append-only log file rotated by size and/or time period.
+/// Rotated files are renamed to `<name>.<timestamp>`, with a `-NNN` counter when
+/// that name is taken, and pruned to `max_files`.
+pub struct RotatingFile {
+    path: PathBuf,
+    config: RotationConfig,
+    file: File,
+    size: u64,
+    period: Option<String>,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, config: RotationConfig) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = open_append(path)?;
+        let size = file.metadata()?.len();
+        let period = period_key(config.period, Utc::now());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            config,
+            file,
+            size,
+            period,
+        })
+    }
+
+    fn should_rotate(&self, incoming: usize, now: DateTime<Utc>) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+
+        let too_large = self
+            .config
+            .max_size
+            .is_some_and(|max| self.size + incoming as u64 > max);
+
+        too_large || period_key(self.config.period, now) != self.period
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        self.file.flush()?;
+
+        fs::rename(&self.path, self.rotated_path(now))?;
+
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        self.period = period_key(self.config.period, now);
+
+        self.prune()
+    }
+
+    // A counter keeps rotations within the same millisecond from replacing
+    // each other, and still sorts after the name without one
+    fn rotated_path(&self, now: DateTime<Utc>) -> PathBuf {
+        let base = format!(
+            "{}.{}",
+            self.path.display(),
+            now.format("%Y%m%d-%H%M%S%.3f")
+        );
+        let mut path = PathBuf::from(&base);
+        let mut counter = 0;
+        while path.exists() {
+            counter += 1;
+            path = PathBuf::from(format!("{}-{:03}", base, counter));
+        }
+        path
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        let Some(file_name) = self.path.file_name().and_then(|n| n.to_str()) else {
+            return Ok(());
+        };
+        let prefix = format!("{}.", file_name);
+        let dir = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut rotated: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with(&prefix))
+            })
+            .collect();
+
+        // Timestamps sort chronologically, oldest first
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(self.config.max_files);
+        for path in rotated.into_iter().take(excess) {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = Utc::now();
+        if self.should_rotate(buf.len(), now) {
+            self.rotate(now)?;
+        }
+
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn period_key(period: RotationPeriod, now: DateTime<Utc>) -> Option<String> {
+    match period {
+        RotationPeriod::Never => None,
+        RotationPeriod::Hourly => Some(now.format("%Y%m%d%H").to_string()),
+        RotationPeriod::Daily => Some(now.format("%Y%m%d").to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_rotation_and_pruning() {
+        let dir = std::env::temp_dir().join(format!("sag-rotation-{}", std::process::id()));
+        let path = dir.join("access.log");
+        let config = RotationConfig {
+            max_size: Some(10),
+            period: RotationPeriod::Never,
+            max_files: 2,
+        };
+
+        let mut file = RotatingFile::open(&path, config).unwrap();
+        for _ in 0..5 {
+            file.write_all(b"12345678\n").unwrap();
+        }
+
+        let files = fs::read_dir(&dir).unwrap().count();
+        assert_eq!(files, 3); // active file plus two rotated
+        assert_eq!(fs::read_to_string(&path).unwrap(), "12345678\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotations_in_the_same_millisecond() {
+        let dir = std::env::temp_dir().join(format!("sag-rotation-ms-{}", std::process::id()));
+        let path = dir.join("access.log");
+        let config = RotationConfig {
+            max_size: Some(2),
+            period: RotationPeriod::Never,
+            max_files: 10,
+        };
+        let now = Utc::now();
+
+        let mut file = RotatingFile::open(&path, config).unwrap();
+        file.write_all(b"a\n").unwrap();
+        file.rotate(now).unwrap();
+        file.write_all(b"b\n").unwrap();
+        file.rotate(now).unwrap();
+        file.write_all(b"c\n").unwrap();
+
+        let mut names: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|p| p != &path)
+            .collect();
+        names.sort();
+        let contents: Vec<String> = names
+            .iter()
+            .map(|p| fs::read_to_string(p).unwrap())
+            .collect();
+        assert_eq!(contents, vec!["a\n", "b\n"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "c\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_period_key() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T13:45:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(period_key(RotationPeriod::Never, now), None);
+        assert_eq!(
+            period_key(RotationPeriod::Daily, now).as_deref(),
+            Some("20250601")
+        );
+        assert_eq!(
+            period_key(RotationPeriod::Hourly, now).as_deref(),
+            Some("2025060113")
+        );
+    }
+}
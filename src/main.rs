@@ -1,55 +1,114 @@
 // src/main.rs
+mod cli;
 mod config;
 mod logging;
 mod server;
 
 use anyhow::Result;
-use clap::{Arg, Command};
-use std::path::PathBuf;
+use clap::ArgMatches;
+use config::AppConfig;
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
 #[allow(unused_imports)]
 use tracing::{debug, error, info};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let matches = Command::new(env!("CARGO_PKG_NAME"))
-        .version(env!("CARGO_PKG_VERSION"))
-        .author(env!("CARGO_PKG_AUTHORS"))
-        .about(env!("CARGO_PKG_DESCRIPTION"))
-        .arg(
-            Arg::new("config")
-                .short('c')
-                .long("config")
-                .value_name("FILE")
-                .help("Path to the config file")
-                .value_parser(clap::value_parser!(PathBuf))
-                .required(false),
-        )
-        .get_matches();
-
-    let config_path_opt = matches.get_one::<PathBuf>("config");
-    let mut app_config = config::load_config(config_path_opt.map(|p| p.as_ref()))?;
+    let matches = cli::command().get_matches();
+    let config_path = matches.get_one::<PathBuf>("config").cloned();
+
+    match matches.subcommand() {
+        Some(("validate", _)) => {
+            let app_config = load(config_path.as_deref())?;
+            println!("Configuration OK ({} routes)", app_config.routes.len());
+            Ok(())
+        }
+        Some(("routes", _)) => cli::print_routes(load(config_path.as_deref())?),
+        Some(("match", args)) => {
+            let method = args.get_one::<String>("method").expect("required");
+            let url = args.get_one::<String>("url").expect("required");
+            cli::print_match(load(config_path.as_deref())?, method, url)
+        }
+        Some(("config", args)) => match args.subcommand() {
+            Some(("dump", args)) => {
+                cli::dump_config(load(config_path.as_deref())?, args.get_flag("json"))
+            }
+            _ => unreachable!("a config subcommand is required"),
+        },
+        Some(("schema", _)) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&config::schema::config_schema())?
+            );
+            Ok(())
+        }
+        Some(("version", _)) => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        Some(("run", args)) => run(config_path, Some(args)).await,
+        _ => run(config_path, None).await,
+    }
+}
+
+// Load the config the way the server does, reporting every problem found
+fn load(config_path: Option<&Path>) -> Result<AppConfig> {
+    let (app_config, problems) = config::validate::check(config_path);
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    app_config.ok_or_else(|| anyhow::anyhow!("Configuration is invalid"))
+}
+
+async fn run(config_path: Option<PathBuf>, args: Option<&ArgMatches>) -> Result<()> {
+    let mut app_config = load(config_path.as_deref())?;
+
+    // Command line flags take precedence over the file and environment
+    if let Some(args) = args {
+        if let Some(host) = args.get_one::<IpAddr>("host") {
+            app_config.server.host = *host;
+        }
+        if let Some(port) = args.get_one::<u16>("port") {
+            app_config.server.port = *port;
+        }
+        if let Some(level) = args.get_one::<String>("log-level") {
+            app_config.logging.level = level.clone();
+        }
+        if args.get_flag("debug") {
+            app_config.debug = true;
+        }
+    }
 
     // Override log level if debug is enabled
     if app_config.debug {
         app_config.logging.level = "debug".to_string();
     }
 
-    // Initialize logging early
-    if let Err(e) = logging::init_logging(&app_config.logging) {
-        eprintln!("Failed to initialize logging: {}", e);
-        return Err(e);
-    }
+    // Initialize logging early, the guard flushes file logs on exit
+    let _log_guard = match logging::init_logging(&app_config.logging) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Failed to initialize logging: {}", e);
+            return Err(e);
+        }
+    };
 
     info!("Starting Simple API Gateway (SAG)");
     debug!("Configuration loaded");
-    debug!("{:#?}", app_config);
+    // Interpolated secrets and the admin token must not reach the logs
+    match serde_json::to_value(&app_config) {
+        Ok(config) => debug!("{:#}", server::admin::redact_config(config)),
+        Err(e) => debug!("Failed to serialize configuration: {}", e),
+    }
 
     if app_config.debug {
         debug!("Debug mode enabled");
     }
 
     // Start the server
-    if let Err(e) = server::start_server(app_config).await {
+    if let Err(e) = server::start_server(app_config, config_path).await {
         error!("Server failed: {}", e);
         return Err(e);
     }
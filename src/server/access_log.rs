@@ -0,0 +1,127 @@
+use crate::logging::access::{AccessLogger, AccessRecord};
+use crate::server::{body::on_body_end, listener::peer_ip, request_id};
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, header},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Route that handled the request, attached to the response for logging
+#[derive(Debug, Clone)]
+pub struct MatchedRoute {
+    pub path: String,
+    pub target: String,
+}
+
+/// Time spent waiting for the upstream, attached to proxied responses
+#[derive(Debug, Clone, Copy)]
+pub struct UpstreamLatency(pub Duration);
+
+pub async fn access_log_middleware(
+    State(logger): State<Arc<AccessLogger>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let time = Utc::now();
+
+    let referer = header_value(&request, header::REFERER);
+    let user_agent = header_value(&request, header::USER_AGENT);
+    let client_ip = peer_ip(request.extensions()).map(|ip| ip.to_string());
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let query = request.uri().query().map(str::to_string);
+    let protocol = format!("{:?}", request.version());
+
+    let response = next.run(request).await;
+
+    let matched = response.extensions().get::<MatchedRoute>();
+    let mut record = AccessRecord {
+        time,
+        client_ip,
+        request_id: request_id::current(),
+        method,
+        path,
+        query,
+        protocol,
+        route: matched.map(|m| m.path.clone()),
+        target: matched.map(|m| m.target.clone()),
+        status: response.status().as_u16(),
+        bytes: 0,
+        upstream_latency: response
+            .extensions()
+            .get::<UpstreamLatency>()
+            .map(|latency| latency.0),
+        total_latency: Duration::ZERO,
+        referer,
+        user_agent,
+    };
+
+    // Streamed bodies are still being sent here, so log once they're done
+    on_body_end(response, move |sent| {
+        record.bytes = sent;
+        record.total_latency = started.elapsed();
+        logger.log(&record);
+    })
+}
+
+fn header_value(request: &Request, name: HeaderName) -> Option<String> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::config::{AccessLogConfig, AccessLogFormat};
+    use axum::{Router, body::Body, routing::get};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_logs_streamed_bytes() {
+        let path = std::env::temp_dir().join(format!("sag-access-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let logger = Arc::new(
+            AccessLogger::new(&AccessLogConfig {
+                enabled: true,
+                format: AccessLogFormat::Custom,
+                template: Some("{status} {bytes}".to_string()),
+                path: Some(path.clone()),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    let chunks: Vec<Result<&str, std::io::Error>> = vec![Ok("hello "), Ok("world")];
+                    Body::from_stream(futures_util::stream::iter(chunks))
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                logger.clone(),
+                access_log_middleware,
+            ));
+
+        let response = app.oneshot(Request::new(Body::empty())).await.unwrap();
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        // Dropping the last handle flushes the writer
+        drop(logger);
+
+        let logged = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(logged.trim(), "200 11");
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,393 @@
+use crate::config::RouteAction;
+use crate::server::{cache::etag_matches, error::ServerError, matcher::RouteMatch};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Uri, header},
+    response::Response,
+};
+use std::{path::Path, time::UNIX_EPOCH};
+use tower_http::services::{ServeDir, ServeFile};
+use tracing::{debug, error};
+
+const DEFAULT_MAINTENANCE_PAGE: &str = "<!DOCTYPE html>\n<html>\n<head><title>Maintenance</title></head>\n<body>\n<h1>Service under maintenance</h1>\n<p>We'll be back shortly.</p>\n</body>\n</html>\n";
+
+/// Build a response for routes that don't proxy to an upstream
+pub async fn respond(
+    action: &RouteAction,
+    route_match: &RouteMatch,
+    request: Request,
+) -> Result<Response<Body>, ServerError> {
+    match action {
+        RouteAction::Proxy => Err(ServerError::InternalError(
+            "Proxy routes are not handled by actions".to_string(),
+        )),
+
+        RouteAction::Redirect { status, location } => {
+            let location = route_match.render(location);
+            debug!("Redirecting to {} ({})", location, status);
+
+            Response::builder()
+                .status(parse_status(*status)?)
+                .header(header::LOCATION, location)
+                .body(Body::empty())
+                .map_err(build_error)
+        }
+
+        RouteAction::Static {
+            status,
+            headers,
+            body,
+            file,
+        } => {
+            let content = match (body, file) {
+                (Some(body), _) => body.clone().into_bytes(),
+                (None, Some(path)) => tokio::fs::read(path).await.map_err(|e| {
+                    error!("Failed to read static file {}: {}", path.display(), e);
+                    ServerError::InternalError(format!(
+                        "Failed to read static file: {}",
+                        path.display()
+                    ))
+                })?,
+                (None, None) => Vec::new(),
+            };
+
+            let mut builder = Response::builder().status(parse_status(*status)?);
+            for (name, value) in headers {
+                let name = HeaderName::try_from(name.as_str()).map_err(|e| {
+                    ServerError::InternalError(format!("Invalid header name {}: {}", name, e))
+                })?;
+                let value = HeaderValue::try_from(route_match.render_raw(value)).map_err(|e| {
+                    ServerError::InternalError(format!("Invalid header value: {}", e))
+                })?;
+                builder = builder.header(name, value);
+            }
+
+            // Like ServeDir, files get a type from their extension unless one is configured
+            if let (None, Some(path)) = (body, file)
+                && !headers
+                    .keys()
+                    .any(|name| name.eq_ignore_ascii_case(header::CONTENT_TYPE.as_str()))
+            {
+                let mime = mime_guess::from_path(path).first_or_octet_stream();
+                builder = builder.header(header::CONTENT_TYPE, mime.as_ref());
+            }
+
+            builder.body(Body::from(content)).map_err(build_error)
+        }
+
+        RouteAction::Maintenance {
+            status,
+            retry_after,
+            content_type,
+            body,
+        } => {
+            let content = body
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MAINTENANCE_PAGE.to_string());
+
+            let mut builder = Response::builder()
+                .status(parse_status(*status)?)
+                .header(header::CONTENT_TYPE, content_type.as_str());
+            if let Some(seconds) = retry_after {
+                builder = builder.header(header::RETRY_AFTER, seconds.to_string());
+            }
+
+            builder.body(Body::from(content)).map_err(build_error)
+        }
+
+        RouteAction::Directory {
+            root,
+            strip_prefix,
+            index,
+            spa_fallback,
+            precompressed,
+        } => {
+            let prefix = strip_prefix.as_deref().unwrap_or("");
+            let request = strip_request_prefix(request, prefix)?;
+            debug!("Serving {} from {}", request.uri().path(), root.display());
+
+            serve_directory(root, *index, *spa_fallback, *precompressed, request).await
+        }
+    }
+}
+
+async fn serve_directory(
+    root: &Path,
+    index: bool,
+    spa_fallback: bool,
+    precompressed: bool,
+    mut request: Request,
+) -> Result<Response<Body>, ServerError> {
+    // If-Modified-Since only applies without If-None-Match (RFC 9110 13.1.3)
+    let if_none_match = request.headers_mut().remove(header::IF_NONE_MATCH);
+    if if_none_match.is_some() {
+        request.headers_mut().remove(header::IF_MODIFIED_SINCE);
+    }
+
+    let mut serve_dir = ServeDir::new(root).append_index_html_on_directories(index);
+    if precompressed {
+        serve_dir = serve_dir.precompressed_br().precompressed_gzip();
+    }
+
+    let result = if spa_fallback {
+        let mut index_file = ServeFile::new(root.join("index.html"));
+        if precompressed {
+            index_file = index_file.precompressed_br().precompressed_gzip();
+        }
+        serve_dir
+            .fallback(index_file)
+            .try_call(request)
+            .await
+            .map(|response| response.map(Body::new))
+    } else {
+        serve_dir
+            .try_call(request)
+            .await
+            .map(|response| response.map(Body::new))
+    };
+
+    let mut response = result.map_err(|e| {
+        error!("Failed to serve file from {}: {}", root.display(), e);
+        ServerError::InternalError("Failed to serve file".to_string())
+    })?;
+
+    // ServeDir only validates with Last-Modified, so add an ETag as well
+    let Some(etag) = file_etag(response.headers()) else {
+        return Ok(response);
+    };
+    if response.status().is_success()
+        && if_none_match.is_some_and(|condition| etag_matches(&condition, &etag))
+    {
+        let mut builder = Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag);
+        if let Some(modified) = response.headers().get(header::LAST_MODIFIED) {
+            builder = builder.header(header::LAST_MODIFIED, modified);
+        }
+        return builder.body(Body::empty()).map_err(build_error);
+    }
+    response.headers_mut().insert(header::ETAG, etag);
+    Ok(response)
+}
+
+// Weak ETag from the modification time and full size of the served file
+fn file_etag(headers: &HeaderMap) -> Option<HeaderValue> {
+    let modified = headers.get(header::LAST_MODIFIED)?.to_str().ok()?;
+    let modified = httpdate::parse_http_date(modified).ok()?;
+    let seconds = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+    // Range responses carry the full size after the slash
+    let size = match headers.get(header::CONTENT_RANGE) {
+        Some(range) => range.to_str().ok()?.rsplit('/').next()?,
+        None => headers.get(header::CONTENT_LENGTH)?.to_str().ok()?,
+    };
+    let size: u64 = size.parse().ok()?;
+
+    HeaderValue::try_from(format!("W/\"{:x}-{:x}\"", seconds, size)).ok()
+}
+
+fn strip_request_prefix(mut request: Request, prefix: &str) -> Result<Request, ServerError> {
+    let uri = request.uri();
+    // Only whole segments are stripped, so /app leaves /application alone
+    let path = match uri.path().strip_prefix(prefix) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/') => rest,
+        _ => uri.path(),
+    };
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    };
+
+    let path_and_query = match uri.query() {
+        Some(q) => format!("{}?{}", path, q),
+        None => path,
+    };
+
+    *request.uri_mut() = path_and_query
+        .parse::<Uri>()
+        .map_err(|e| ServerError::RequestError(format!("Invalid path: {}", e)))?;
+
+    Ok(request)
+}
+
+fn parse_status(status: u16) -> Result<StatusCode, ServerError> {
+    StatusCode::from_u16(status)
+        .map_err(|_| ServerError::InternalError(format!("Invalid status code: {}", status)))
+}
+
+fn build_error(e: axum::http::Error) -> ServerError {
+    error!("Failed to build response: {}", e);
+    ServerError::InternalError("Failed to build response".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RouteConfig;
+    use std::collections::HashMap;
+
+    fn route_match(params: &[(&str, &str)]) -> RouteMatch {
+        RouteMatch {
+            route: RouteConfig::default(),
+            params: params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirect() {
+        let action = RouteAction::Redirect {
+            status: 301,
+            location: "https://new.example.com/users/{id}".to_string(),
+        };
+
+        let response = respond(&action, &route_match(&[("id", "7")]), Request::default())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://new.example.com/users/7"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_static_body() {
+        let action = RouteAction::Static {
+            status: 410,
+            headers: HashMap::from([("content-type".to_string(), "text/plain".to_string())]),
+            body: Some("gone".to_string()),
+            file: None,
+        };
+
+        let response = respond(&action, &route_match(&[]), Request::default())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GONE);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"gone");
+    }
+
+    #[tokio::test]
+    async fn test_static_file_type() {
+        let path = std::env::temp_dir().join(format!("sag-static-{}.json", std::process::id()));
+        std::fs::write(&path, "{}").unwrap();
+
+        let action = RouteAction::Static {
+            status: 200,
+            headers: HashMap::new(),
+            body: None,
+            file: Some(path.clone()),
+        };
+        let response = respond(&action, &route_match(&[]), Request::default())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_directory_spa_fallback() {
+        let root = std::env::temp_dir().join(format!("sag-static-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("assets")).unwrap();
+        std::fs::write(root.join("index.html"), "<html>app</html>").unwrap();
+        std::fs::write(root.join("assets/app.js"), "console.log(1)").unwrap();
+
+        let action = RouteAction::Directory {
+            root: root.clone(),
+            strip_prefix: Some("/app".to_string()),
+            index: true,
+            spa_fallback: true,
+            precompressed: false,
+        };
+
+        let request = Request::builder()
+            .uri("/app/assets/app.js")
+            .body(Body::empty())
+            .unwrap();
+        let response = respond(&action, &route_match(&[]), request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/javascript");
+        assert!(response.headers().contains_key(header::LAST_MODIFIED));
+        let etag = response.headers()[header::ETAG].clone();
+        assert!(etag.to_str().unwrap().starts_with("W/\""));
+
+        // A matching ETag wins over an If-Modified-Since that would not match
+        let request = Request::builder()
+            .uri("/app/assets/app.js")
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .header(header::IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT")
+            .body(Body::empty())
+            .unwrap();
+        let response = respond(&action, &route_match(&[]), request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+
+        let request = Request::builder()
+            .uri("/app/assets/app.js")
+            .header(header::IF_NONE_MATCH, "W/\"other\"")
+            .body(Body::empty())
+            .unwrap();
+        let response = respond(&action, &route_match(&[]), request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = Request::builder()
+            .uri("/app/users/42")
+            .body(Body::empty())
+            .unwrap();
+        let response = respond(&action, &route_match(&[]), request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"<html>app</html>");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_strip_request_prefix() {
+        let request = Request::builder()
+            .uri("/app/js/main.js?v=1")
+            .body(Body::empty())
+            .unwrap();
+
+        let request = strip_request_prefix(request, "/app").unwrap();
+        assert_eq!(request.uri(), "/js/main.js?v=1");
+
+        for (uri, prefix, stripped) in [
+            ("/application/x", "/app", "/application/x"),
+            ("/app", "/app", "/"),
+            ("/app/x", "/app/", "/x"),
+        ] {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let request = strip_request_prefix(request, prefix).unwrap();
+            assert_eq!(request.uri(), stripped);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_maintenance() {
+        let action = RouteAction::Maintenance {
+            status: 503,
+            retry_after: Some(120),
+            content_type: "text/html".to_string(),
+            body: None,
+        };
+
+        let response = respond(&action, &route_match(&[]), Request::default())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "120");
+    }
+}
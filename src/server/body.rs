@@ -0,0 +1,183 @@
+use axum::{
+    body::{Body, Bytes},
+    response::Response,
+};
+use http_body::{Frame, SizeHint};
+use http_body_util::BodyExt;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+type OnEnd = Box<dyn FnOnce(u64) + Send>;
+
+/// Response body reporting how many bytes it sent once the stream ends,
+/// fails or is dropped because the client went away
+struct ObservedBody {
+    inner: Body,
+    sent: u64,
+    on_end: Option<OnEnd>,
+}
+
+impl ObservedBody {
+    fn finish(&mut self) {
+        if let Some(on_end) = self.on_end.take() {
+            on_end(self.sent);
+        }
+    }
+}
+
+impl http_body::Body for ObservedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    self.sent += data.len() as u64;
+                }
+            }
+            Poll::Ready(None) | Poll::Ready(Some(Err(_))) => self.finish(),
+            Poll::Pending => {}
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for ObservedBody {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Call `on_end` with the number of body bytes sent once the response is
+/// done, rather than when its headers are ready
+pub fn on_body_end(response: Response, on_end: impl FnOnce(u64) + Send + 'static) -> Response {
+    response.map(|inner| {
+        Body::new(ObservedBody {
+            inner,
+            sent: 0,
+            on_end: Some(Box::new(on_end)),
+        })
+    })
+}
+
+/// Outcome of reading a body into memory with a size limit
+pub enum Buffered {
+    Complete(Bytes),
+    /// The limit was passed; the body still yields everything, starting
+    /// with the part already read
+    TooLarge(Body),
+}
+
+/// Read a body of unknown length into memory, giving up once it grows past
+/// `limit` so large responses keep streaming
+pub async fn buffer_up_to(mut body: Body, limit: usize) -> Result<Buffered, axum::Error> {
+    let mut buffered = Vec::new();
+    while let Some(frame) = body.frame().await {
+        // Trailers are dropped, as with any buffered body
+        let Ok(data) = frame?.into_data() else {
+            continue;
+        };
+        buffered.extend_from_slice(&data);
+        if buffered.len() > limit {
+            return Ok(Buffered::TooLarge(Body::new(Prefixed {
+                head: Some(Bytes::from(buffered)),
+                rest: body,
+            })));
+        }
+    }
+    Ok(Buffered::Complete(Bytes::from(buffered)))
+}
+
+/// Body yielding bytes already read ahead of the rest of the stream
+struct Prefixed {
+    head: Option<Bytes>,
+    rest: Body,
+}
+
+impl http_body::Body for Prefixed {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match self.head.take() {
+            Some(head) => Poll::Ready(Some(Ok(Frame::data(head)))),
+            None => Pin::new(&mut self.rest).poll_frame(cx),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.head.is_none() && self.rest.is_end_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_reports_bytes_at_end() {
+        let reported = Arc::new(Mutex::new(None));
+        let chunks: Vec<Result<&'static str, std::io::Error>> = vec![Ok("hello "), Ok("world")];
+        let response = Response::new(Body::from_stream(futures_util::stream::iter(chunks)));
+
+        let sink = reported.clone();
+        let response = on_body_end(response, move |sent| *sink.lock().unwrap() = Some(sent));
+        assert_eq!(*reported.lock().unwrap(), None);
+
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(*reported.lock().unwrap(), Some(11));
+    }
+
+    #[tokio::test]
+    async fn test_buffer_up_to() {
+        let chunks = || {
+            let chunks: Vec<Result<&str, std::io::Error>> = vec![Ok("hello "), Ok("world")];
+            Body::from_stream(futures_util::stream::iter(chunks))
+        };
+
+        match buffer_up_to(chunks(), 11).await.unwrap() {
+            Buffered::Complete(bytes) => assert_eq!(bytes, "hello world"),
+            Buffered::TooLarge(_) => panic!("fits the limit"),
+        }
+        match buffer_up_to(chunks(), 5).await.unwrap() {
+            Buffered::Complete(_) => panic!("exceeds the limit"),
+            Buffered::TooLarge(body) => {
+                let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+                assert_eq!(bytes, "hello world");
+            }
+        }
+    }
+
+    #[test]
+    fn test_reports_on_drop() {
+        let reported = Arc::new(Mutex::new(None));
+        let sink = reported.clone();
+        let response = on_body_end(Response::new(Body::from("unsent")), move |sent| {
+            *sink.lock().unwrap() = Some(sent)
+        });
+
+        drop(response);
+        assert_eq!(*reported.lock().unwrap(), Some(0));
+    }
+}
@@ -0,0 +1,709 @@
+use crate::config::CacheConfig;
+use crate::server::{
+    body::{Buffered, buffer_up_to},
+    error::ServerError,
+    proxy::ProxyClient,
+};
+use axum::{
+    body::{Body, Bytes},
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, header},
+    response::Response,
+};
+use lru::LruCache;
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+use tracing::{debug, error};
+
+const X_CACHE: &str = "x-cache";
+
+// Uncacheable keys remembered to skip request coalescing
+const UNCACHEABLE_KEYS: NonZeroUsize = NonZeroUsize::new(10_000).unwrap();
+
+// Statuses that are cacheable by default (RFC 9110 section 15.1)
+const CACHEABLE_STATUSES: [u16; 7] = [200, 203, 204, 300, 301, 404, 410];
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    stored_at: Instant,
+    ttl: Duration,
+}
+
+impl CachedResponse {
+    fn is_fresh(&self) -> bool {
+        self.stored_at.elapsed() < self.ttl
+    }
+
+    fn matches_vary(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+
+    fn size(&self) -> usize {
+        let headers: usize = self
+            .headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+        self.body.len() + headers
+    }
+
+    fn to_response(&self, request_headers: &HeaderMap) -> Response<Body> {
+        // If-Modified-Since only applies without If-None-Match (RFC 9110 13.1.3)
+        let not_modified = match request_headers.get(header::IF_NONE_MATCH) {
+            Some(condition) => self
+                .headers
+                .get(header::ETAG)
+                .is_some_and(|etag| etag_matches(condition, etag)),
+            None => not_modified_since(request_headers, &self.headers),
+        };
+
+        let mut response = if not_modified {
+            Response::new(Body::empty())
+        } else {
+            Response::new(Body::from(self.body.clone()))
+        };
+        *response.status_mut() = if not_modified {
+            StatusCode::NOT_MODIFIED
+        } else {
+            self.status
+        };
+        *response.headers_mut() = self.headers.clone();
+
+        let age = self.stored_at.elapsed().as_secs();
+        response
+            .headers_mut()
+            .insert(header::AGE, HeaderValue::from(age));
+        response
+            .headers_mut()
+            .insert(X_CACHE, HeaderValue::from_static("HIT"));
+        response
+    }
+}
+
+struct Store {
+    entries: LruCache<String, Vec<CachedResponse>>,
+    size: usize,
+}
+
+/// In-memory, size-bounded LRU cache for upstream GET responses
+pub struct ResponseCache {
+    store: Mutex<Store>,
+    inflight: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    // Keys whose last response could not be stored, until one can
+    uncacheable: Mutex<LruCache<String, ()>>,
+    max_size: usize,
+    max_entry_size: usize,
+}
+
+impl ResponseCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        Self {
+            store: Mutex::new(Store {
+                entries: LruCache::unbounded(),
+                size: 0,
+            }),
+            inflight: Mutex::new(HashMap::new()),
+            uncacheable: Mutex::new(LruCache::new(UNCACHEABLE_KEYS)),
+            max_size: config.max_size,
+            max_entry_size: config.max_entry_size,
+        }
+    }
+
+    /// Serve a request from the cache, fetching it through the proxy on a miss.
+    /// Concurrent misses for the same key share a single upstream fetch.
+    pub async fn fetch(
+        &self,
+        proxy: &ProxyClient,
+        request: Request,
+        target: &str,
+        ttl_override: Option<Duration>,
+    ) -> Result<Response<Body>, ServerError> {
+        if !is_cacheable_request(&request) {
+            return proxy.proxy_request(request, target).await;
+        }
+
+        let key = cache_key(target, request.uri());
+
+        if let Some(entry) = self.lookup(&key, request.headers())
+            && entry.is_fresh()
+        {
+            debug!("Cache hit: {}", key);
+            return Ok(entry.to_response(request.headers()));
+        }
+
+        // Keys known to be uncacheable are not coalesced, waiting would only
+        // turn concurrent requests into sequential upstream calls
+        let lock = self.inflight_lock(&key);
+        let guard = match self.is_uncacheable(&key) {
+            true => None,
+            false => Some(lock.lock().await),
+        };
+        // The request we waited for may have found the response uncacheable
+        let guard = guard.filter(|_| !self.is_uncacheable(&key));
+        let result = self
+            .fetch_locked(proxy, request, target, &key, ttl_override)
+            .await;
+        drop(guard);
+        drop(lock);
+        self.release_inflight(&key);
+
+        result
+    }
+
+    async fn fetch_locked(
+        &self,
+        proxy: &ProxyClient,
+        mut request: Request,
+        target: &str,
+        key: &str,
+        ttl_override: Option<Duration>,
+    ) -> Result<Response<Body>, ServerError> {
+        let request_headers = request.headers().clone();
+        let stale = self.lookup(key, &request_headers);
+
+        // Another request may have filled the entry while we waited
+        if let Some(entry) = &stale
+            && entry.is_fresh()
+        {
+            debug!("Cache hit after coalescing: {}", key);
+            return Ok(entry.to_response(&request_headers));
+        }
+
+        // Client validators are answered from the cache, the upstream sees ours
+        let headers = request.headers_mut();
+        headers.remove(header::IF_NONE_MATCH);
+        headers.remove(header::IF_MODIFIED_SINCE);
+        if let Some(entry) = &stale {
+            if let Some(etag) = entry.headers.get(header::ETAG) {
+                headers.insert(header::IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(modified) = entry.headers.get(header::LAST_MODIFIED) {
+                headers.insert(header::IF_MODIFIED_SINCE, modified.clone());
+            }
+        }
+
+        debug!("Cache miss: {}", key);
+        let response = proxy.proxy_request(request, target).await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED
+            && let Some(mut entry) = stale
+        {
+            debug!("Cache entry revalidated: {}", key);
+            for (name, value) in response.headers() {
+                entry.headers.insert(name.clone(), value.clone());
+            }
+            entry.stored_at = Instant::now();
+            entry.ttl = freshness_lifetime(response.headers(), ttl_override).unwrap_or_default();
+            self.insert(key, entry.clone());
+            return Ok(entry.to_response(&request_headers));
+        }
+
+        let (mut parts, body) = response.into_parts();
+        parts
+            .headers
+            .insert(X_CACHE, HeaderValue::from_static("MISS"));
+
+        let storable = CACHEABLE_STATUSES.contains(&parts.status.as_u16())
+            && !parts.headers.contains_key(header::SET_COOKIE)
+            && content_length(&parts.headers).is_none_or(|len| len <= self.max_entry_size);
+        let (ttl, vary) = match (
+            storable,
+            freshness_lifetime(&parts.headers, ttl_override),
+            vary_values(&parts.headers, &request_headers),
+        ) {
+            (true, Some(ttl), Some(vary)) => (ttl, vary),
+            // Streamed straight through, it will not be stored
+            _ => {
+                self.mark_uncacheable(key);
+                return Ok(Response::from_parts(parts, body));
+            }
+        };
+
+        // Chunked responses are buffered until they turn out too large
+        let body = match buffer_up_to(body, self.max_entry_size).await {
+            Ok(Buffered::Complete(body)) => body,
+            Ok(Buffered::TooLarge(body)) => {
+                debug!("Response too large to cache: {}", key);
+                self.mark_uncacheable(key);
+                return Ok(Response::from_parts(parts, body));
+            }
+            Err(e) => {
+                error!("Failed to buffer response body: {}", e);
+                return Err(ServerError::ProxyError(
+                    "Failed to read response body".to_string(),
+                ));
+            }
+        };
+
+        let mut headers = parts.headers.clone();
+        headers.remove(X_CACHE);
+        self.insert(
+            key,
+            CachedResponse {
+                status: parts.status,
+                headers,
+                body: body.clone(),
+                vary,
+                stored_at: Instant::now(),
+                ttl,
+            },
+        );
+        Ok(Response::from_parts(parts, Body::from(body)))
+    }
+
+    fn lookup(&self, key: &str, headers: &HeaderMap) -> Option<CachedResponse> {
+        let mut store = self.store.lock().unwrap();
+        store
+            .entries
+            .get(key)?
+            .iter()
+            .find(|entry| entry.matches_vary(headers))
+            .cloned()
+    }
+
+    fn insert(&self, key: &str, entry: CachedResponse) {
+        let entry_size = entry.size();
+        if entry_size > self.max_entry_size {
+            debug!("Response too large to cache: {} bytes", entry_size);
+            self.mark_uncacheable(key);
+            return;
+        }
+        self.uncacheable.lock().unwrap().pop(key);
+
+        let mut store = self.store.lock().unwrap();
+        let mut freed = 0;
+        let variants = store.entries.get_or_insert_mut(key.to_string(), Vec::new);
+        variants.retain(|existing| {
+            let same_variant = existing.vary == entry.vary;
+            if same_variant {
+                freed += existing.size();
+            }
+            !same_variant
+        });
+        variants.push(entry);
+        store.size = store.size - freed + entry_size;
+
+        while store.size > self.max_size {
+            match store.entries.pop_lru() {
+                Some((evicted, variants)) => {
+                    debug!("Evicting cache entry: {}", evicted);
+                    store.size -= variants.iter().map(CachedResponse::size).sum::<usize>();
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn is_uncacheable(&self, key: &str) -> bool {
+        self.uncacheable.lock().unwrap().contains(key)
+    }
+
+    fn mark_uncacheable(&self, key: &str) {
+        self.uncacheable.lock().unwrap().put(key.to_string(), ());
+    }
+
+    fn inflight_lock(&self, key: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.inflight
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default()
+            .clone()
+    }
+
+    fn release_inflight(&self, key: &str) {
+        let mut inflight = self.inflight.lock().unwrap();
+        // Only the map itself holds the lock once every waiter is done
+        if inflight
+            .get(key)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            inflight.remove(key);
+        }
+    }
+}
+
+fn is_cacheable_request(request: &Request) -> bool {
+    if request.method() != Method::GET || request.headers().contains_key(header::AUTHORIZATION) {
+        return false;
+    }
+
+    let directives = cache_control(request.headers());
+    !directives.contains_key("no-store") && !directives.contains_key("no-cache")
+}
+
+fn cache_key(target: &str, uri: &Uri) -> String {
+    let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+    format!("{}{}", target.trim_end_matches('/'), path_and_query)
+}
+
+fn content_length(headers: &HeaderMap) -> Option<usize> {
+    headers
+        .get(header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+fn cache_control(headers: &HeaderMap) -> HashMap<String, Option<String>> {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|directive| {
+            let mut parts = directive.trim().splitn(2, '=');
+            let name = parts.next()?.trim().to_lowercase();
+            if name.is_empty() {
+                return None;
+            }
+            let value = parts.next().map(|v| v.trim().trim_matches('"').to_string());
+            Some((name, value))
+        })
+        .collect()
+}
+
+// How long a response may be served without revalidation, None if it must not be stored
+fn freshness_lifetime(headers: &HeaderMap, ttl_override: Option<Duration>) -> Option<Duration> {
+    let directives = cache_control(headers);
+    if directives.contains_key("no-store") || directives.contains_key("private") {
+        return None;
+    }
+
+    if ttl_override.is_some() {
+        return ttl_override;
+    }
+
+    if directives.contains_key("no-cache") {
+        return Some(Duration::ZERO);
+    }
+
+    let max_age = |name: &str| {
+        directives
+            .get(name)
+            .and_then(|value| value.as_deref()?.parse::<u64>().ok())
+            .map(Duration::from_secs)
+    };
+    if let Some(ttl) = max_age("s-maxage").or_else(|| max_age("max-age")) {
+        return Some(ttl);
+    }
+
+    let expires = headers.get(header::EXPIRES)?.to_str().ok()?;
+    // Invalid dates (e.g. "0") mean already expired
+    let expires = httpdate::parse_http_date(expires).unwrap_or(SystemTime::UNIX_EPOCH);
+    Some(
+        expires
+            .duration_since(SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+// Request header values the response varies on, None for `Vary: *`
+fn vary_values(
+    response_headers: &HeaderMap,
+    request_headers: &HeaderMap,
+) -> Option<Vec<(HeaderName, Option<HeaderValue>)>> {
+    let mut values = Vec::new();
+
+    for value in response_headers.get_all(header::VARY) {
+        for name in value.to_str().ok()?.split(',') {
+            let name = name.trim();
+            if name == "*" {
+                return None;
+            }
+            if let Ok(name) = HeaderName::try_from(name) {
+                let value = request_headers.get(&name).cloned();
+                values.push((name, value));
+            }
+        }
+    }
+
+    Some(values)
+}
+
+fn not_modified_since(request_headers: &HeaderMap, response_headers: &HeaderMap) -> bool {
+    let date = |headers: &HeaderMap, name| {
+        httpdate::parse_http_date(headers.get(name)?.to_str().ok()?).ok()
+    };
+    match (
+        date(request_headers, header::IF_MODIFIED_SINCE),
+        date(response_headers, header::LAST_MODIFIED),
+    ) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+/// Weak comparison of an If-None-Match list against an ETag
+pub fn etag_matches(condition: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(condition) = condition.to_str() else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default().trim_start_matches("W/");
+
+    condition.trim() == "*"
+        || condition
+            .split(',')
+            .any(|candidate| candidate.trim().trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::get};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    fn entry(body: &'static str, vary: Vec<(HeaderName, Option<HeaderValue>)>) -> CachedResponse {
+        CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+            vary,
+            stored_at: Instant::now(),
+            ttl: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        let lifetime = |pairs| freshness_lifetime(&headers(pairs), None);
+
+        assert_eq!(
+            lifetime(&[("cache-control", "public, max-age=60")]),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            lifetime(&[("cache-control", "max-age=60, s-maxage=10")]),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(lifetime(&[("cache-control", "no-store")]), None);
+        assert_eq!(lifetime(&[("cache-control", "private, max-age=60")]), None);
+        assert_eq!(
+            lifetime(&[("cache-control", "no-cache")]),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(lifetime(&[("expires", "0")]), Some(Duration::ZERO));
+        assert_eq!(lifetime(&[]), None);
+
+        assert_eq!(
+            freshness_lifetime(&headers(&[]), Some(Duration::from_secs(5))),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_vary_lookup() {
+        let cache = ResponseCache::new(&CacheConfig::default());
+        let gzip = headers(&[("accept-encoding", "gzip")]);
+        let response_headers = headers(&[("vary", "Accept-Encoding")]);
+
+        let vary = vary_values(&response_headers, &gzip).unwrap();
+        cache.insert("key", entry("gzip", vary));
+
+        assert!(cache.lookup("key", &gzip).is_some());
+        assert!(cache.lookup("key", &HeaderMap::new()).is_none());
+        assert!(vary_values(&headers(&[("vary", "*")]), &gzip).is_none());
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = ResponseCache::new(&CacheConfig {
+            enabled: true,
+            max_size: 10,
+            max_entry_size: 10,
+        });
+
+        cache.insert("a", entry("aaaaaa", Vec::new()));
+        cache.insert("b", entry("bbbbbb", Vec::new()));
+        cache.insert("c", entry("ccccccccccc", Vec::new()));
+
+        assert!(cache.lookup("a", &HeaderMap::new()).is_none());
+        assert!(cache.lookup("b", &HeaderMap::new()).is_some());
+        assert!(cache.lookup("c", &HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = HeaderValue::from_static("\"abc\"");
+
+        assert!(etag_matches(&HeaderValue::from_static("\"abc\""), &etag));
+        assert!(etag_matches(
+            &HeaderValue::from_static("W/\"abc\", \"x\""),
+            &etag
+        ));
+        assert!(etag_matches(&HeaderValue::from_static("*"), &etag));
+        assert!(!etag_matches(&HeaderValue::from_static("\"x\""), &etag));
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        let mut cached = entry("body", Vec::new());
+        cached.headers = headers(&[("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT")]);
+
+        let response = cached.to_response(&headers(&[(
+            "if-modified-since",
+            "Wed, 21 Oct 2015 07:28:00 GMT",
+        )]));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let response = cached.to_response(&headers(&[(
+            "if-modified-since",
+            "Tue, 20 Oct 2015 07:28:00 GMT",
+        )]));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    async fn serve_upstream(hits: Arc<AtomicUsize>, headers: HeaderMap) -> String {
+        let upstream = Router::new().route(
+            "/data",
+            get(move || {
+                let (hits, headers) = (hits.clone(), headers.clone());
+                async move {
+                    hits.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    (headers, "payload")
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+        target
+    }
+
+    #[tokio::test]
+    async fn test_chunked_responses() {
+        let upstream = Router::new().route(
+            "/data",
+            get(|| async {
+                let chunks: Vec<Result<&str, std::io::Error>> = vec![Ok("chunked "), Ok("payload")];
+                (
+                    [(header::CACHE_CONTROL, "max-age=60")],
+                    Body::from_stream(futures_util::stream::iter(chunks)),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        let proxy = ProxyClient::new();
+        async fn fetch(
+            cache: &ResponseCache,
+            proxy: &ProxyClient,
+            target: &str,
+        ) -> (String, Bytes) {
+            let request = Request::builder().uri("/data").body(Body::empty()).unwrap();
+            let response = cache.fetch(proxy, request, target, None).await.unwrap();
+            let status = response.headers()[X_CACHE].to_str().unwrap().to_string();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, body)
+        }
+
+        let cache = ResponseCache::new(&CacheConfig::default());
+        assert_eq!(
+            fetch(&cache, &proxy, &target).await,
+            ("MISS".to_string(), "chunked payload".into())
+        );
+        assert_eq!(
+            fetch(&cache, &proxy, &target).await,
+            ("HIT".to_string(), "chunked payload".into())
+        );
+
+        // Past the entry limit the response streams through in full, uncached
+        let cache = ResponseCache::new(&CacheConfig {
+            max_entry_size: 10,
+            ..CacheConfig::default()
+        });
+        assert_eq!(
+            fetch(&cache, &proxy, &target).await,
+            ("MISS".to_string(), "chunked payload".into())
+        );
+        assert_eq!(
+            fetch(&cache, &proxy, &target).await,
+            ("MISS".to_string(), "chunked payload".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_uncacheable_responses() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let target = serve_upstream(
+            hits.clone(),
+            headers(&[("cache-control", "max-age=60"), ("set-cookie", "session=1")]),
+        )
+        .await;
+        let cache = ResponseCache::new(&CacheConfig::default());
+        let proxy = ProxyClient::new();
+        let request = || Request::builder().uri("/data").body(Body::empty()).unwrap();
+
+        // Responses setting cookies are never replayed to other clients
+        cache.fetch(&proxy, request(), &target, None).await.unwrap();
+        let response = cache.fetch(&proxy, request(), &target, None).await.unwrap();
+        assert_eq!(response.headers()[X_CACHE], "MISS");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // Once known to be uncacheable, concurrent requests are not serialized
+        let started = Instant::now();
+        let (a, b, c) = tokio::join!(
+            cache.fetch(&proxy, request(), &target, None),
+            cache.fetch(&proxy, request(), &target, None),
+            cache.fetch(&proxy, request(), &target, None),
+        );
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        assert!(started.elapsed() < Duration::from_millis(140));
+        assert!(cache.inflight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_fetch() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let target =
+            serve_upstream(hits.clone(), headers(&[("cache-control", "max-age=60")])).await;
+
+        let cache = Arc::new(ResponseCache::new(&CacheConfig::default()));
+        let proxy = Arc::new(ProxyClient::new());
+
+        let requests = (0..5).map(|_| {
+            let (cache, proxy, target) = (cache.clone(), proxy.clone(), target.clone());
+            tokio::spawn(async move {
+                let request = Request::builder().uri("/data").body(Body::empty()).unwrap();
+                cache.fetch(&proxy, request, &target, None).await.unwrap()
+            })
+        });
+
+        let mut statuses = Vec::new();
+        for request in requests {
+            let response = request.await.unwrap();
+            statuses.push(response.headers()[X_CACHE].to_str().unwrap().to_string());
+        }
+
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert_eq!(statuses.iter().filter(|s| *s == "MISS").count(), 1);
+        assert_eq!(statuses.iter().filter(|s| *s == "HIT").count(), 4);
+    }
+}
@@ -0,0 +1,428 @@
+use crate::config::{CompressionConfig, Encoding};
+use crate::server::error::ServerError;
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::Request,
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    response::Response,
+};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use std::io::{Read, Write};
+use tracing::{debug, error};
+
+impl Encoding {
+    fn token(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// Pick the preferred encoding the client accepts, honouring q-values
+pub fn negotiate(accept_encoding: Option<&HeaderValue>, offered: &[Encoding]) -> Option<Encoding> {
+    let accept = accept_encoding?.to_str().ok()?;
+
+    let accepted: Vec<(String, f32)> = accept
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let coding = parts.next()?.trim().to_lowercase();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((coding, quality))
+        })
+        .collect();
+
+    let quality_of = |encoding: &Encoding| {
+        accepted
+            .iter()
+            .find(|(coding, _)| coding == encoding.token())
+            .or_else(|| accepted.iter().find(|(coding, _)| coding == "*"))
+            .map(|(_, q)| *q)
+            .unwrap_or(0.0)
+    };
+
+    // Highest quality wins, ties go to the server preference order
+    let mut best: Option<(Encoding, f32)> = None;
+    for encoding in offered {
+        let quality = quality_of(encoding);
+        if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+            best = Some((*encoding, quality));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Compress a response when the client and the configuration allow it.
+/// Only bodies of known length up to `max_size` are buffered for this.
+pub async fn compress_response(
+    response: Response<Body>,
+    config: &CompressionConfig,
+    method: &Method,
+    accept_encoding: Option<&HeaderValue>,
+) -> Result<Response<Body>, ServerError> {
+    if !is_compressible(&response, config, method) {
+        return Ok(response);
+    }
+
+    let Some(length) = body_length(&response) else {
+        return Ok(response);
+    };
+    if length < config.min_size || length > config.max_size {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+
+    // The representation depends on Accept-Encoding from here on
+    append_vary(&mut parts.headers);
+
+    let Some(encoding) = negotiate(accept_encoding, &config.encodings) else {
+        return Ok(Response::from_parts(parts, body));
+    };
+
+    let body = axum::body::to_bytes(body, config.max_size)
+        .await
+        .map_err(|e| {
+            error!("Failed to buffer response body: {}", e);
+            ServerError::ProxyError("Failed to read response body".to_string())
+        })?;
+
+    // Compression is CPU bound, keep it off the async workers
+    let input = body.clone();
+    let compressed = tokio::task::spawn_blocking(move || encode(encoding, &input))
+        .await
+        .map_err(std::io::Error::other)
+        .and_then(|result| result)
+        .map_err(|e| {
+            error!("Failed to compress response: {}", e);
+            ServerError::InternalError("Failed to compress response".to_string())
+        })?;
+    debug!(
+        "Compressed response with {}: {} -> {} bytes",
+        encoding.token(),
+        body.len(),
+        compressed.len()
+    );
+
+    let headers = &mut parts.headers;
+    headers.insert(
+        header::CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.token()),
+    );
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(compressed.len()));
+    headers.remove(header::ACCEPT_RANGES);
+    // A strong ETag would no longer identify these exact bytes
+    if let Some(etag) = headers.get(header::ETAG).and_then(|v| v.to_str().ok())
+        && !etag.starts_with("W/")
+        && let Ok(weak) = HeaderValue::from_str(&format!("W/{}", etag))
+    {
+        headers.insert(header::ETAG, weak);
+    }
+
+    Ok(Response::from_parts(parts, Body::from(compressed)))
+}
+
+/// Replace a compressed request body with its decoded form. Both the
+/// compressed and the decoded body are limited to `limit` bytes.
+pub async fn decompress_request(request: Request, limit: usize) -> Result<Request, ServerError> {
+    let Some(coding) = request
+        .headers()
+        .get(header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_lowercase())
+    else {
+        return Ok(request);
+    };
+
+    let too_large =
+        || ServerError::PayloadTooLarge(format!("Request body exceeds {} bytes", limit));
+
+    let (mut parts, body) = request.into_parts();
+    let body = match Limited::new(body, limit).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) if e.downcast_ref::<LengthLimitError>().is_some() => return Err(too_large()),
+        Err(e) => {
+            error!("Failed to read request body: {}", e);
+            return Err(ServerError::RequestError(
+                "Failed to read request body".to_string(),
+            ));
+        }
+    };
+
+    let compressed = body.clone();
+    let decode_coding = coding.clone();
+    let decoded = tokio::task::spawn_blocking(move || decode(&decode_coding, &compressed, limit))
+        .await
+        .map_err(|e| ServerError::InternalError(format!("Decoding task failed: {}", e)))?
+        .map_err(|e| {
+            debug!("Failed to decode {} request body: {}", coding, e);
+            ServerError::RequestError(format!("Invalid {} request body", coding))
+        })?
+        .ok_or_else(too_large)?;
+    debug!(
+        "Decompressed {} request body: {} -> {} bytes",
+        coding,
+        body.len(),
+        decoded.len()
+    );
+
+    parts.headers.remove(header::CONTENT_ENCODING);
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(decoded.len()));
+
+    Ok(Request::from_parts(parts, Body::from(decoded)))
+}
+
+// Content-Length, or the exact size of bodies the gateway built itself
+fn body_length(response: &Response<Body>) -> Option<usize> {
+    response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok()?.parse().ok())
+        .or_else(|| {
+            let exact = response.body().size_hint().exact()?;
+            usize::try_from(exact).ok()
+        })
+}
+
+fn is_compressible(response: &Response<Body>, config: &CompressionConfig, method: &Method) -> bool {
+    let headers = response.headers();
+
+    if method == Method::HEAD
+        || matches!(
+            response.status(),
+            StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED | StatusCode::PARTIAL_CONTENT
+        )
+        || headers.contains_key(header::CONTENT_ENCODING)
+    {
+        return false;
+    }
+
+    let no_transform = headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.to_lowercase().contains("no-transform"));
+    if no_transform {
+        return false;
+    }
+
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    config
+        .content_types
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(&mime))
+}
+
+fn append_vary(headers: &mut HeaderMap) {
+    let already_varies = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|name| {
+            let name = name.trim();
+            name == "*" || name.eq_ignore_ascii_case("accept-encoding")
+        });
+
+    if !already_varies {
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+fn encode(encoding: Encoding, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    match encoding {
+        Encoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        Encoding::Brotli => {
+            let mut output = Vec::new();
+            {
+                let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 5, 22);
+                encoder.write_all(data)?;
+            }
+            Ok(output)
+        }
+        Encoding::Zstd => zstd::encode_all(data, 3),
+    }
+}
+
+// Decoded body, or None when it would exceed `limit` bytes
+fn decode(coding: &str, data: &Bytes, limit: usize) -> std::io::Result<Option<Vec<u8>>> {
+    let reader: Box<dyn Read> = match coding {
+        "gzip" | "x-gzip" => Box::new(flate2::read::GzDecoder::new(&data[..])),
+        "deflate" => Box::new(flate2::read::ZlibDecoder::new(&data[..])),
+        "br" => Box::new(brotli::Decompressor::new(&data[..], 4096)),
+        "zstd" => Box::new(zstd::stream::read::Decoder::new(&data[..])?),
+        "identity" => Box::new(&data[..]),
+        other => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unsupported content encoding: {}", other),
+            ));
+        }
+    };
+
+    // Reading one byte past the limit tells a full body from a truncated one
+    let mut output = Vec::new();
+    reader.take(limit as u64 + 1).read_to_end(&mut output)?;
+    Ok((output.len() <= limit).then_some(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_response(body: &str) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+            .header(header::ETAG, "\"v1\"")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn enabled_config() -> CompressionConfig {
+        CompressionConfig {
+            enabled: true,
+            min_size: 16,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let offered = [Encoding::Brotli, Encoding::Zstd, Encoding::Gzip];
+        let negotiate_for =
+            |value: &'static str| negotiate(Some(&HeaderValue::from_static(value)), &offered);
+
+        assert_eq!(negotiate_for("gzip, br"), Some(Encoding::Brotli));
+        assert_eq!(negotiate_for("gzip;q=1.0, br;q=0.5"), Some(Encoding::Gzip));
+        assert_eq!(negotiate_for("identity"), None);
+        assert_eq!(negotiate_for("*"), Some(Encoding::Brotli));
+        assert_eq!(negotiate_for("*, br;q=0"), Some(Encoding::Zstd));
+        assert_eq!(negotiate(None, &offered), None);
+    }
+
+    #[tokio::test]
+    async fn test_compress_response() {
+        let body = "{\"message\": \"hello hello hello hello hello\"}";
+        let accept = HeaderValue::from_static("gzip");
+
+        let response = compress_response(
+            json_response(body),
+            &enabled_config(),
+            &Method::GET,
+            Some(&accept),
+        )
+        .await
+        .unwrap();
+
+        let headers = response.headers();
+        assert_eq!(headers[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(headers[header::VARY], "accept-encoding");
+        assert_eq!(headers[header::ETAG], "W/\"v1\"");
+
+        let compressed = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let decoded = decode("gzip", &compressed, usize::MAX - 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded, body.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_skip_small_and_unlisted() {
+        let accept = HeaderValue::from_static("gzip");
+        let config = enabled_config();
+
+        let response = compress_response(json_response("{}"), &config, &Method::GET, Some(&accept))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+
+        let image = Response::builder()
+            .header(header::CONTENT_TYPE, "image/png")
+            .body(Body::from(vec![0u8; 64]))
+            .unwrap();
+        let response = compress_response(image, &config, &Method::GET, Some(&accept))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert!(!response.headers().contains_key(header::VARY));
+
+        // Streamed bodies of unknown length are passed through
+        let stream = Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from_stream(futures_util::stream::iter([Ok::<
+                _,
+                std::io::Error,
+            >(
+                Bytes::from(vec![b' '; 64]),
+            )])))
+            .unwrap();
+        let response = compress_response(stream, &config, &Method::GET, Some(&accept))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn test_decompress_request() {
+        let compressed = encode(Encoding::Zstd, b"payload").unwrap();
+        let request = Request::builder()
+            .header(header::CONTENT_ENCODING, "zstd")
+            .body(Body::from(compressed))
+            .unwrap();
+
+        let request = decompress_request(request, 1024).await.unwrap();
+        assert!(!request.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(request.headers()[header::CONTENT_LENGTH], "7");
+
+        let body = axum::body::to_bytes(request.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"payload");
+    }
+
+    #[tokio::test]
+    async fn test_decompression_limit() {
+        // A small gzip body that expands well past the limit
+        let bomb = encode(Encoding::Gzip, &vec![0u8; 64 * 1024]).unwrap();
+        let request = Request::builder()
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(Body::from(bomb))
+            .unwrap();
+
+        let result = decompress_request(request, 1024).await;
+        assert!(matches!(result, Err(ServerError::PayloadTooLarge(_))));
+
+        let request = Request::builder()
+            .header(header::CONTENT_ENCODING, "identity")
+            .body(Body::from(vec![0u8; 2048]))
+            .unwrap();
+        let result = decompress_request(request, 1024).await;
+        assert!(matches!(result, Err(ServerError::PayloadTooLarge(_))));
+    }
+}
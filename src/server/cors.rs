@@ -0,0 +1,342 @@
+use crate::config::CorsConfig;
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    response::Response,
+};
+use tracing::debug;
+
+/// A CORS preflight is an OPTIONS request announcing the real method
+pub fn is_preflight(method: &Method, headers: &HeaderMap) -> bool {
+    method == Method::OPTIONS
+        && headers.contains_key(header::ORIGIN)
+        && headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// Answer a preflight request without contacting the upstream
+pub fn preflight_response(
+    config: &CorsConfig,
+    headers: &HeaderMap,
+    route_methods: &[String],
+) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NO_CONTENT;
+
+    let origin = headers.get(header::ORIGIN);
+    let requested_method = headers
+        .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    let allowed_methods = if config.allowed_methods.is_empty() {
+        route_methods
+    } else {
+        &config.allowed_methods
+    };
+    let method_allowed = allowed_methods.is_empty()
+        || allowed_methods
+            .iter()
+            .any(|m| m == "*" || m.eq_ignore_ascii_case(requested_method));
+
+    let requested_headers = headers
+        .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let headers_allowed = requested_headers
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .all(|name| {
+            config
+                .allowed_headers
+                .iter()
+                .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(name))
+        });
+
+    append_vary(response.headers_mut());
+
+    if !method_allowed || !headers_allowed || !apply_origin(config, origin, &mut response) {
+        debug!(
+            "CORS preflight rejected: origin={:?} method={} headers={}",
+            origin, requested_method, requested_headers
+        );
+        return response;
+    }
+
+    let response_headers = response.headers_mut();
+    let methods = if allowed_methods.is_empty() {
+        requested_method.to_string()
+    } else {
+        allowed_methods.join(", ")
+    };
+    if let Ok(value) = HeaderValue::from_str(&methods) {
+        response_headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, value);
+    }
+
+    let allow_any_header = config.allowed_headers.iter().any(|h| h == "*");
+    let allowed_headers = if allow_any_header {
+        // Echo the request so "*" also works with credentials
+        requested_headers.to_string()
+    } else {
+        config.allowed_headers.join(", ")
+    };
+    if !allowed_headers.is_empty()
+        && let Ok(value) = HeaderValue::from_str(&allowed_headers)
+    {
+        response_headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, value);
+    }
+
+    response_headers.insert(
+        header::ACCESS_CONTROL_MAX_AGE,
+        HeaderValue::from(config.max_age),
+    );
+
+    response
+}
+
+/// Replace whatever CORS headers the upstream sent with the gateway policy
+pub fn decorate_response(
+    config: &CorsConfig,
+    origin: Option<&HeaderValue>,
+    response: &mut Response<Body>,
+) {
+    let headers = response.headers_mut();
+    for name in [
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        header::ACCESS_CONTROL_ALLOW_HEADERS,
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        header::ACCESS_CONTROL_MAX_AGE,
+    ] {
+        headers.remove(name);
+    }
+    append_vary(headers);
+
+    if apply_origin(config, origin, response)
+        && !config.exposed_headers.is_empty()
+        && let Ok(value) = HeaderValue::from_str(&config.exposed_headers.join(", "))
+    {
+        response
+            .headers_mut()
+            .insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, value);
+    }
+}
+
+// Set Allow-Origin (and Allow-Credentials) when the origin is allowed
+fn apply_origin(
+    config: &CorsConfig,
+    origin: Option<&HeaderValue>,
+    response: &mut Response<Body>,
+) -> bool {
+    let Some(origin) = origin else {
+        return false;
+    };
+    let Ok(origin_str) = origin.to_str() else {
+        return false;
+    };
+
+    // Only origins listed explicitly are reflected and get credentials
+    let listed = config
+        .allowed_origins
+        .iter()
+        .any(|pattern| pattern != "*" && origin_matches(pattern, origin_str));
+    let any_origin = config.allowed_origins.iter().any(|o| o == "*");
+    if !listed && !any_origin {
+        return false;
+    }
+
+    let headers = response.headers_mut();
+    if listed {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        if config.allow_credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    } else {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        );
+    }
+
+    true
+}
+
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    if pattern == "*" || pattern.eq_ignore_ascii_case(origin) {
+        return true;
+    }
+
+    // "https://*.example.com" matches any subdomain, but not the apex
+    let Some((scheme, host_pattern)) = pattern.split_once("://") else {
+        return false;
+    };
+    let Some(suffix) = host_pattern.strip_prefix('*') else {
+        return false;
+    };
+    let Some(origin_host) = origin
+        .strip_prefix(scheme)
+        .and_then(|rest| rest.strip_prefix("://"))
+    else {
+        return false;
+    };
+
+    origin_host.len() > suffix.len() && origin_host.to_lowercase().ends_with(&suffix.to_lowercase())
+}
+
+fn append_vary(headers: &mut HeaderMap) {
+    let varies = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|name| name.trim().eq_ignore_ascii_case("origin"));
+
+    if !varies {
+        headers.append(header::VARY, HeaderValue::from_static("origin"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CorsConfig {
+        CorsConfig {
+            enabled: true,
+            allowed_origins: vec![
+                "https://app.example.com".to_string(),
+                "https://*.example.org".to_string(),
+            ],
+            exposed_headers: vec!["x-request-id".to_string()],
+            allow_credentials: true,
+            ..Default::default()
+        }
+    }
+
+    fn preflight_headers(origin: &'static str, method: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ORIGIN, HeaderValue::from_static(origin));
+        headers.insert(
+            header::ACCESS_CONTROL_REQUEST_METHOD,
+            HeaderValue::from_static(method),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_origin_matches() {
+        assert!(origin_matches("*", "https://anything.com"));
+        assert!(origin_matches(
+            "https://app.example.com",
+            "https://app.example.com"
+        ));
+        assert!(origin_matches(
+            "https://*.example.org",
+            "https://a.b.example.org"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.org",
+            "https://example.org"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.org",
+            "http://a.example.org"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.org",
+            "https://evilexample.org"
+        ));
+    }
+
+    #[test]
+    fn test_preflight() {
+        let methods = vec!["GET".to_string(), "POST".to_string()];
+        let headers = preflight_headers("https://x.example.org", "POST");
+        assert!(is_preflight(&Method::OPTIONS, &headers));
+
+        let response = preflight_response(&config(), &headers, &methods);
+        let response_headers = response.headers();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            response_headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://x.example.org"
+        );
+        assert_eq!(
+            response_headers[header::ACCESS_CONTROL_ALLOW_METHODS],
+            "GET, POST"
+        );
+        assert_eq!(
+            response_headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS],
+            "true"
+        );
+        assert_eq!(response_headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+
+        let headers = preflight_headers("https://x.example.org", "DELETE");
+        let response = preflight_response(&config(), &headers, &methods);
+        assert!(
+            !response
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+    }
+
+    #[test]
+    fn test_decorate_response() {
+        let mut response = Response::builder()
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .body(Body::empty())
+            .unwrap();
+        let origin = HeaderValue::from_static("https://app.example.com");
+
+        decorate_response(&config(), Some(&origin), &mut response);
+        let headers = response.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "x-request-id"
+        );
+        assert_eq!(headers[header::VARY], "origin");
+
+        let mut response = Response::builder()
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .body(Body::empty())
+            .unwrap();
+        let origin = HeaderValue::from_static("https://evil.com");
+        decorate_response(&config(), Some(&origin), &mut response);
+        assert!(
+            !response
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+    }
+
+    #[test]
+    fn test_any_origin_without_credentials() {
+        let mut config = config();
+        config.allowed_origins.push("*".to_string());
+
+        let mut response = Response::new(Body::empty());
+        let origin = HeaderValue::from_static("https://evil.com");
+        decorate_response(&config, Some(&origin), &mut response);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let mut response = Response::new(Body::empty());
+        let origin = HeaderValue::from_static("https://app.example.com");
+        decorate_response(&config, Some(&origin), &mut response);
+        let headers = response.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    }
+}
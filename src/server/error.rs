@@ -1,3 +1,4 @@
+use crate::server::request_id;
 use axum::{
     Json,
     http::StatusCode,
@@ -6,12 +7,20 @@ use axum::{
 use serde_json::json;
 use std::fmt;
 
+/// What went wrong, kept on error responses until their body is rendered
+#[derive(Debug, Clone)]
+pub struct ErrorInfo {
+    pub error: &'static str,
+    pub detail: Option<String>,
+}
+
 #[derive(Debug)]
 pub enum ServerError {
     ProxyError(String),
     RouteNotFound,
     InvalidTarget(String),
     RequestError(String),
+    PayloadTooLarge(String),
     InternalError(String),
 }
 
@@ -22,6 +31,7 @@ impl fmt::Display for ServerError {
             ServerError::RouteNotFound => write!(f, "Route not found"),
             ServerError::InvalidTarget(target) => write!(f, "Invalid target: {}", target),
             ServerError::RequestError(msg) => write!(f, "Request error: {}", msg),
+            ServerError::PayloadTooLarge(msg) => write!(f, "Payload too large: {}", msg),
             ServerError::InternalError(msg) => write!(f, "Internal error: {}", msg),
         }
     }
@@ -44,6 +54,11 @@ impl IntoResponse for ServerError {
                 Some(format!("Invalid target: {}", target)),
             ),
             ServerError::RequestError(msg) => (StatusCode::BAD_REQUEST, "Bad request", Some(msg)),
+            ServerError::PayloadTooLarge(msg) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Payload too large",
+                Some(msg),
+            ),
             ServerError::InternalError(msg) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal server error",
@@ -51,19 +66,19 @@ impl IntoResponse for ServerError {
             ),
         };
 
-        // For now, we'll always include debug info
-        // Later we'll make this conditional based on debug mode
-        let body = if let Some(debug) = debug_info {
-            json!({
-                "error": error_message,
-                "debug": debug
-            })
-        } else {
-            json!({
-                "error": error_message
-            })
-        };
+        // Details are added by error page rendering, and only in debug mode
+        let mut body = json!({
+            "error": error_message
+        });
+        if let Some(request_id) = request_id::current() {
+            body["request_id"] = json!(request_id);
+        }
 
-        (status, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        response.extensions_mut().insert(ErrorInfo {
+            error: error_message,
+            detail: debug_info,
+        });
+        response
     }
 }
@@ -0,0 +1,254 @@
+use crate::config::{ErrorFormat, ErrorPage, ErrorPagesConfig};
+use crate::server::{access_log::UpstreamLatency, error::ErrorInfo, request_id};
+use axum::{
+    body::Body,
+    http::{HeaderValue, StatusCode, header},
+    response::Response,
+};
+use serde_json::json;
+use tracing::warn;
+
+/// Render the body of a gateway error, or of an upstream 5xx when configured.
+/// Other responses are returned untouched.
+pub async fn render(config: &ErrorPagesConfig, debug: bool, mut response: Response) -> Response {
+    let status = response.status();
+    let info = match response.extensions_mut().remove::<ErrorInfo>() {
+        Some(info) => info,
+        None if is_mapped_upstream_error(config, &response) => ErrorInfo {
+            error: status.canonical_reason().unwrap_or("Upstream error"),
+            detail: Some(format!("Upstream responded with {}", status)),
+        },
+        None => return response,
+    };
+
+    let detail = info.detail.as_deref().filter(|_| debug);
+    let page = match config.page(status.as_u16()) {
+        Some(page) => page_body(page, config.format, status, info.error, detail).await,
+        None => None,
+    };
+    let (content_type, body) =
+        page.unwrap_or_else(|| default_body(config.format, status, info.error, detail));
+
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.remove(header::CONTENT_ENCODING);
+    if let Ok(value) = HeaderValue::from_str(&content_type) {
+        parts.headers.insert(header::CONTENT_TYPE, value);
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn is_mapped_upstream_error(config: &ErrorPagesConfig, response: &Response) -> bool {
+    let status = response.status();
+    config.map_upstream_5xx
+        && status.is_server_error()
+        && response.extensions().get::<UpstreamLatency>().is_some()
+        && config.page(status.as_u16()).is_some()
+}
+
+async fn page_body(
+    page: &ErrorPage,
+    format: ErrorFormat,
+    status: StatusCode,
+    error: &str,
+    detail: Option<&str>,
+) -> Option<(String, String)> {
+    let template = match (&page.body, &page.file) {
+        (Some(body), _) => body.clone(),
+        (None, Some(file)) => match tokio::fs::read_to_string(file).await {
+            Ok(template) => template,
+            Err(e) => {
+                warn!("Failed to read error page {}: {}", file.display(), e);
+                return None;
+            }
+        },
+        (None, None) => return None,
+    };
+
+    let content_type = page
+        .content_type
+        .clone()
+        .unwrap_or_else(|| format.content_type().to_string());
+    let escape = escaper(&content_type);
+    let body = template
+        .replace("{status}", status.as_str())
+        .replace("{error}", &escape(error))
+        .replace(
+            "{request_id}",
+            &escape(&request_id::current().unwrap_or_default()),
+        )
+        .replace("{detail}", &escape(detail.unwrap_or_default()));
+
+    Some((content_type, body))
+}
+
+/// Values substituted into templates are escaped for the page's content type
+fn escaper(content_type: &str) -> fn(&str) -> String {
+    if content_type.contains("json") {
+        escape_json
+    } else if content_type.contains("html") || content_type.contains("xml") {
+        escape_html
+    } else {
+        str::to_string
+    }
+}
+
+fn escape_json(value: &str) -> String {
+    let quoted = json!(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn default_body(
+    format: ErrorFormat,
+    status: StatusCode,
+    error: &str,
+    detail: Option<&str>,
+) -> (String, String) {
+    let request_id = request_id::current();
+    let body = match format {
+        ErrorFormat::Json => {
+            let mut body = json!({ "error": error });
+            if let Some(detail) = detail {
+                body["debug"] = json!(detail);
+            }
+            if let Some(request_id) = request_id {
+                body["request_id"] = json!(request_id);
+            }
+            body.to_string()
+        }
+        ErrorFormat::Problem => {
+            let mut body = json!({
+                "type": "about:blank",
+                "title": error,
+                "status": status.as_u16(),
+            });
+            if let Some(detail) = detail {
+                body["detail"] = json!(detail);
+            }
+            if let Some(request_id) = request_id {
+                body["request_id"] = json!(request_id);
+            }
+            body.to_string()
+        }
+        ErrorFormat::Html => {
+            let mut body = format!(
+                "<!DOCTYPE html>\n<html>\n<head><title>{status} {error}</title></head>\n<body>\n<h1>{status} {error}</h1>\n",
+                status = status.as_u16(),
+                error = escape_html(error),
+            );
+            if let Some(detail) = detail {
+                body.push_str(&format!("<pre>{}</pre>\n", escape_html(detail)));
+            }
+            if let Some(request_id) = request_id {
+                body.push_str(&format!(
+                    "<p>Request ID: {}</p>\n",
+                    escape_html(&request_id)
+                ));
+            }
+            body.push_str("</body>\n</html>\n");
+            body
+        }
+    };
+
+    (format.content_type().to_string(), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::error::ServerError;
+    use axum::response::IntoResponse;
+    use std::time::Duration;
+
+    async fn body_string(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    fn proxy_error() -> Response {
+        ServerError::ProxyError("connection refused".to_string()).into_response()
+    }
+
+    #[tokio::test]
+    async fn test_debug_details() {
+        let config = ErrorPagesConfig::default();
+
+        let body = body_string(render(&config, false, proxy_error()).await).await;
+        assert_eq!(body, r#"{"error":"Service temporarily unavailable"}"#);
+
+        let body = body_string(render(&config, true, proxy_error()).await).await;
+        assert!(body.contains(r#""debug":"connection refused""#));
+    }
+
+    #[tokio::test]
+    async fn test_problem_format() {
+        let config = ErrorPagesConfig {
+            format: ErrorFormat::Problem,
+            ..Default::default()
+        };
+
+        let response = render(&config, true, proxy_error()).await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["status"], 502);
+        assert_eq!(body["title"], "Service temporarily unavailable");
+        assert_eq!(body["detail"], "connection refused");
+    }
+
+    #[tokio::test]
+    async fn test_page_template() {
+        let config: ErrorPagesConfig = serde_yaml::from_str(
+            r#"
+format: Html
+map_upstream_5xx: true
+pages:
+  "5xx": { body: "<p>{status}: {error} {detail}</p>" }
+"#,
+        )
+        .unwrap();
+
+        let response = render(&config, true, proxy_error()).await;
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            body_string(response).await,
+            "<p>502: Service temporarily unavailable connection refused</p>"
+        );
+
+        // Upstream 5xx bodies are replaced, other upstream responses are kept
+        let mut upstream = (StatusCode::SERVICE_UNAVAILABLE, "stack trace").into_response();
+        upstream
+            .extensions_mut()
+            .insert(UpstreamLatency(Duration::ZERO));
+        let response = render(&config, false, upstream).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            body_string(response).await,
+            "<p>503: Service Unavailable </p>"
+        );
+
+        let mut upstream = (StatusCode::NOT_FOUND, "missing").into_response();
+        upstream
+            .extensions_mut()
+            .insert(UpstreamLatency(Duration::ZERO));
+        let response = render(&config, false, upstream).await;
+        assert_eq!(body_string(response).await, "missing");
+    }
+}
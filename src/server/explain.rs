@@ -0,0 +1,285 @@
+use crate::config::{ExplainConfig, RouteAction, auth::AuthType};
+use crate::server::{
+    listener::peer_ip,
+    matcher::{RouteEvaluation, RouteMatcher},
+    routes::{is_method_allowed, upstream_url},
+};
+use axum::{
+    extract::Request,
+    http::{
+        HeaderMap, HeaderName, Method, Uri,
+        header::{AUTHORIZATION, InvalidHeaderName},
+    },
+};
+use ipnet::IpNet;
+use serde::Serialize;
+use std::{collections::BTreeMap, path::PathBuf};
+use tracing::debug;
+
+/// Decides which requests get a routing trace instead of being proxied
+pub struct Explainer {
+    header: HeaderName,
+    trusted_networks: Vec<IpNet>,
+}
+
+impl Explainer {
+    pub fn new(config: &ExplainConfig) -> Result<Self, InvalidHeaderName> {
+        Ok(Self {
+            header: HeaderName::try_from(config.header.as_str())?,
+            trusted_networks: config.trusted_networks.clone(),
+        })
+    }
+
+    pub fn requested(&self, request: &Request) -> bool {
+        if !request.headers().contains_key(&self.header) {
+            return false;
+        }
+
+        let trusted = peer_ip(request.extensions()).is_some_and(|ip| {
+            self.trusted_networks
+                .iter()
+                .any(|network| network.contains(&ip))
+        });
+        if !trusted {
+            debug!("Ignoring explain header from an untrusted peer");
+        }
+        trusted
+    }
+}
+
+/// What the gateway would do with a request
+#[derive(Debug, Serialize)]
+pub struct Explanation {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub decision: String,
+    pub routes: Vec<RouteEvaluation>,
+    pub matched: Option<MatchedRoute>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatchedRoute {
+    pub index: usize,
+    pub path: String,
+    pub source: Option<PathBuf>,
+    pub params: BTreeMap<String, String>,
+    pub methods: Vec<String>,
+    pub method_allowed: bool,
+    pub auth: AuthDecision,
+    pub action: RouteAction,
+    pub upstream: Option<String>,
+    pub upstream_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthDecision {
+    pub required: bool,
+    pub auth_type: AuthType,
+    pub roles: Vec<String>,
+    pub credentials_present: bool,
+    // Route auth settings are not enforced by the gateway yet
+    pub enforced: bool,
+}
+
+pub fn explain(
+    matcher: &RouteMatcher,
+    listener: Option<&str>,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+) -> Explanation {
+    let routes = matcher.evaluate(listener, uri.path(), uri.query());
+    let found = matcher.find_indexed_on(listener, uri.path(), uri.query());
+
+    let matched = found.map(|(index, route_match)| {
+        let route = &route_match.route;
+        let upstream_url = match route.action {
+            RouteAction::Proxy => {
+                Some(upstream_url(&route_match, uri).unwrap_or_else(|e| format!("<{}>", e)))
+            }
+            _ => None,
+        };
+
+        MatchedRoute {
+            index,
+            path: route.path.clone(),
+            source: route.source.clone(),
+            params: route_match.params.clone().into_iter().collect(),
+            methods: route.methods.clone(),
+            method_allowed: is_method_allowed(&route.methods, method),
+            auth: AuthDecision {
+                required: route.auth.required,
+                auth_type: route.auth.auth_type.clone(),
+                roles: route.auth.roles.clone(),
+                credentials_present: has_credentials(&route.auth.auth_type, headers),
+                enforced: false,
+            },
+            action: route.action.clone(),
+            upstream: route.upstream.clone(),
+            upstream_url,
+        }
+    });
+
+    let decision = match &matched {
+        None => "not_found".to_string(),
+        Some(matched) if !matched.method_allowed => "method_not_allowed".to_string(),
+        Some(matched) => matched.action.name().to_lowercase(),
+    };
+
+    Explanation {
+        method: method.to_string(),
+        path: uri.path().to_string(),
+        query: uri.query().map(str::to_string),
+        decision,
+        routes,
+        matched,
+    }
+}
+
+fn has_credentials(auth_type: &AuthType, headers: &HeaderMap) -> bool {
+    let authorization = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+    match auth_type {
+        AuthType::Bearer => authorization.is_some_and(|v| v.starts_with("Bearer ")),
+        AuthType::Basic => authorization.is_some_and(|v| v.starts_with("Basic ")),
+        AuthType::ApiKey => headers.contains_key("x-api-key"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MatchType, QueryPredicate, RouteConfig};
+    use crate::server::matcher::MatchOutcome;
+    use axum::{body::Body, extract::ConnectInfo};
+    use std::net::SocketAddr;
+
+    fn matcher() -> RouteMatcher {
+        let mut beta = RouteConfig {
+            path: "/api".to_string(),
+            target: "http://beta".to_string(),
+            match_type: MatchType::Prefix,
+            ..Default::default()
+        };
+        beta.query.predicates = vec![QueryPredicate {
+            name: "beta".to_string(),
+            value: None,
+            absent: false,
+        }];
+
+        RouteMatcher::new(vec![
+            RouteConfig {
+                path: "/health".to_string(),
+                target: "http://health".to_string(),
+                ..Default::default()
+            },
+            beta,
+            RouteConfig {
+                path: "/api/users/{id}".to_string(),
+                target: "http://users".to_string(),
+                match_type: MatchType::Wildcard,
+                methods: vec!["GET".to_string()],
+                ..Default::default()
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_explain() {
+        let uri: Uri = "/api/users/42?x=1".parse().unwrap();
+        let explanation = explain(&matcher(), None, &Method::GET, &uri, &HeaderMap::new());
+
+        let outcomes: Vec<MatchOutcome> = explanation.routes.iter().map(|r| r.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                MatchOutcome::PathMismatch,
+                MatchOutcome::QueryMismatch,
+                MatchOutcome::Matched
+            ]
+        );
+        assert_eq!(explanation.decision, "proxy");
+
+        let matched = explanation.matched.unwrap();
+        assert_eq!(matched.index, 2);
+        assert_eq!(matched.params["id"], "42");
+        assert_eq!(
+            matched.upstream_url.as_deref(),
+            Some("http://users/api/users/42?x=1")
+        );
+
+        let explanation = explain(&matcher(), None, &Method::DELETE, &uri, &HeaderMap::new());
+        assert_eq!(explanation.decision, "method_not_allowed");
+        let explanation = explain(
+            &matcher(),
+            None,
+            &Method::GET,
+            &"/nope".parse().unwrap(),
+            &HeaderMap::new(),
+        );
+        assert_eq!(explanation.decision, "not_found");
+        assert_eq!(explanation.routes.len(), 3);
+    }
+
+    #[test]
+    fn test_explain_on_listener() {
+        let matcher = RouteMatcher::new(vec![
+            RouteConfig {
+                path: "/admin".to_string(),
+                target: "http://internal".to_string(),
+                listeners: vec!["internal".to_string()],
+                ..Default::default()
+            },
+            RouteConfig {
+                path: "/admin".to_string(),
+                target: "http://public".to_string(),
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+        let uri: Uri = "/admin".parse().unwrap();
+
+        let explanation = explain(
+            &matcher,
+            Some("public"),
+            &Method::GET,
+            &uri,
+            &HeaderMap::new(),
+        );
+        assert_eq!(
+            explanation.routes[0].outcome,
+            MatchOutcome::ListenerMismatch
+        );
+        assert_eq!(explanation.matched.unwrap().index, 1);
+
+        let explanation = explain(
+            &matcher,
+            Some("internal"),
+            &Method::GET,
+            &uri,
+            &HeaderMap::new(),
+        );
+        assert_eq!(explanation.matched.unwrap().index, 0);
+    }
+
+    #[test]
+    fn test_requested_from_trusted_peer() {
+        let explainer = Explainer::new(&ExplainConfig::default()).unwrap();
+        let request = |peer: &str, header: bool| {
+            let mut builder = Request::builder().uri("/");
+            if header {
+                builder = builder.header("x-sag-explain", "1");
+            }
+            let mut request = builder.body(Body::empty()).unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+            request
+        };
+
+        assert!(explainer.requested(&request("127.0.0.1:5000", true)));
+        assert!(!explainer.requested(&request("127.0.0.1:5000", false)));
+        assert!(!explainer.requested(&request("203.0.113.9:5000", true)));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::config::HealthConfig;
+use crate::server::{
+    admin::upstream_keys,
+    route_table::RouteTable,
+    upstreams::{UpstreamHealth, UpstreamState},
+};
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Serialize)]
+struct ReloadOutcome {
+    success: bool,
+    at: String,
+    error: Option<String>,
+}
+
+/// Process state behind the liveness and readiness probes
+pub struct Health {
+    started: Instant,
+    warmup: Duration,
+    shutting_down: AtomicBool,
+    routes: Arc<RouteTable>,
+    upstreams: Arc<UpstreamHealth>,
+    last_reload: Mutex<Option<ReloadOutcome>>,
+}
+
+impl Health {
+    pub fn new(warmup: Duration, routes: Arc<RouteTable>, upstreams: Arc<UpstreamHealth>) -> Self {
+        Self {
+            started: Instant::now(),
+            warmup,
+            shutting_down: AtomicBool::new(false),
+            routes,
+            upstreams,
+            last_reload: Mutex::new(None),
+        }
+    }
+
+    /// Fail readiness from now on so load balancers stop sending traffic
+    pub fn set_shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn record_reload(&self, result: &Result<u64, String>) {
+        let outcome = ReloadOutcome {
+            success: result.is_ok(),
+            at: Utc::now().to_rfc3339(),
+            error: result.as_ref().err().cloned(),
+        };
+        *self.last_reload.lock().expect("health lock poisoned") = Some(outcome);
+    }
+
+    fn uptime(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+}
+
+pub fn health_router<S>(config: &HealthConfig, health: Arc<Health>) -> Router<S> {
+    let mut router = Router::new()
+        .route(&config.liveness_path, get(liveness))
+        .route(&config.readiness_path, get(readiness));
+
+    if let Some(path) = &config.health_path {
+        router = router.route(path, get(health_check));
+    }
+
+    router.with_state(health)
+}
+
+async fn liveness(State(health): State<Arc<Health>>) -> Response {
+    Json(json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "uptime_seconds": health.uptime(),
+    }))
+    .into_response()
+}
+
+async fn readiness(State(health): State<Arc<Health>>) -> Response {
+    let upstreams = health
+        .upstreams
+        .status(&upstream_keys(&health.routes.current().routes()));
+    let all_down = !upstreams.is_empty()
+        && upstreams
+            .iter()
+            .all(|upstream| upstream.state == UpstreamState::Unhealthy);
+
+    let reason = if health.shutting_down.load(Ordering::SeqCst) {
+        Some("shutting_down")
+    } else if health.started.elapsed() < health.warmup {
+        Some("warming_up")
+    } else if all_down {
+        Some("upstreams_unavailable")
+    } else {
+        None
+    };
+
+    let status = if reason.is_none() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = json!({
+        "status": if reason.is_none() { "ready" } else { "not_ready" },
+        "reason": reason,
+        "uptime_seconds": health.uptime(),
+        "config": {
+            "version": health.routes.version(),
+            "last_reload": *health.last_reload.lock().expect("health lock poisoned"),
+        },
+        "upstreams": upstreams,
+    });
+
+    (status, Json(body)).into_response()
+}
+
+// Plain health check kept for older probes
+async fn health_check() -> &'static str {
+    "OK"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RouteConfig;
+    use crate::server::{error::ServerError, matcher::RouteMatcher};
+    use axum::{body::Body, extract::Request};
+    use tower::ServiceExt;
+
+    fn health(warmup: Duration) -> (Arc<Health>, Arc<UpstreamHealth>) {
+        let routes = vec![RouteConfig {
+            path: "/api".to_string(),
+            target: "http://api".to_string(),
+            ..Default::default()
+        }];
+        let routes = Arc::new(RouteTable::new(RouteMatcher::new(routes).unwrap()));
+        let upstreams = Arc::new(UpstreamHealth::new());
+        let health = Arc::new(Health::new(warmup, routes, upstreams.clone()));
+        (health, upstreams)
+    }
+
+    async fn probe(health: &Arc<Health>, path: &str) -> (StatusCode, serde_json::Value) {
+        let router: Router = health_router(&HealthConfig::default(), health.clone());
+        let request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_readiness() {
+        let (health, upstreams) = health(Duration::ZERO);
+
+        let (status, body) = probe(&health, "/readyz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["config"]["version"], 1);
+        assert_eq!(body["upstreams"][0]["upstream"], "http://api");
+        assert_eq!(body["upstreams"][0]["state"], "unknown");
+
+        for _ in 0..3 {
+            upstreams.record(
+                "http://api",
+                &Err(ServerError::ProxyError("refused".to_string())),
+            );
+        }
+        let (status, body) = probe(&health, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["reason"], "upstreams_unavailable");
+
+        health.set_shutting_down();
+        let (_, body) = probe(&health, "/readyz").await;
+        assert_eq!(body["reason"], "shutting_down");
+
+        // Liveness is unaffected
+        let (status, body) = probe(&health, "/livez").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_warmup() {
+        let (health, _) = health(Duration::from_secs(60));
+
+        let (status, body) = probe(&health, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["reason"], "warming_up");
+    }
+}
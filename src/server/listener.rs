@@ -0,0 +1,132 @@
+use crate::config::ListenAddress;
+use axum::{extract::ConnectInfo, http::Extensions};
+use listenfd::ListenFd;
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use {axum::serve::Listener, std::os::unix::net::SocketAddr as UnixAddr, tokio::net::UnixListener};
+
+/// Name of the listener a request arrived on, set as a request extension
+#[derive(Debug, Clone)]
+pub struct ListenerName(pub Arc<str>);
+
+pub enum BoundListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixSocketListener),
+}
+
+/// Unix listener with std peer addresses, which unlike tokio's can be cloned
+/// into `ConnectInfo`
+#[cfg(unix)]
+pub struct UnixSocketListener(UnixListener);
+
+#[cfg(unix)]
+impl Listener for UnixSocketListener {
+    type Io = tokio::net::UnixStream;
+    type Addr = UnixAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let (io, addr) = Listener::accept(&mut self.0).await;
+        (io, addr.into())
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Listener::local_addr(&self.0).map(Into::into)
+    }
+}
+
+/// IP address of the client, for peer-based trust and logging. Peers on Unix
+/// sockets have none, so they are never trusted by network.
+pub fn peer_ip(extensions: &Extensions) -> Option<IpAddr> {
+    extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| peer.ip())
+}
+
+/// Bind an address, taking systemd sockets out of `fds`
+pub async fn bind(address: &ListenAddress, fds: &mut ListenFd) -> io::Result<BoundListener> {
+    match address {
+        ListenAddress::Tcp(addr) => Ok(BoundListener::Tcp(TcpListener::bind(addr).await?)),
+        #[cfg(unix)]
+        ListenAddress::Unix(path) => {
+            use std::os::unix::fs::FileTypeExt;
+
+            // A socket left behind by a previous run would make bind fail
+            if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(path)?;
+            }
+            Ok(BoundListener::Unix(UnixSocketListener(UnixListener::bind(
+                path,
+            )?)))
+        }
+        ListenAddress::Systemd(index) => take_systemd(*index, fds),
+        #[cfg(not(unix))]
+        ListenAddress::Unix(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix domain sockets are not supported on this platform",
+        )),
+    }
+}
+
+fn take_systemd(index: usize, fds: &mut ListenFd) -> io::Result<BoundListener> {
+    let received = fds.len();
+    let missing = || {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "no socket {} was passed by systemd ({} received)",
+                index, received
+            ),
+        )
+    };
+
+    // The fd stays in place when it is not a TCP socket, so Unix can be tried next
+    match fds.take_tcp_listener(index) {
+        Ok(Some(listener)) => {
+            listener.set_nonblocking(true)?;
+            Ok(BoundListener::Tcp(TcpListener::from_std(listener)?))
+        }
+        Ok(None) => Err(missing()),
+        #[cfg(unix)]
+        Err(_) => {
+            let listener = fds.take_unix_listener(index)?.ok_or_else(missing)?;
+            listener.set_nonblocking(true)?;
+            Ok(BoundListener::Unix(UnixSocketListener(
+                UnixListener::from_std(listener)?,
+            )))
+        }
+        #[cfg(not(unix))]
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_ip() {
+        let mut extensions = Extensions::new();
+        assert_eq!(peer_ip(&extensions), None);
+
+        extensions.insert(ConnectInfo("192.0.2.7:5000".parse::<SocketAddr>().unwrap()));
+        assert_eq!(peer_ip(&extensions), Some("192.0.2.7".parse().unwrap()));
+
+        #[cfg(unix)]
+        {
+            let unnamed = std::os::unix::net::UnixDatagram::unbound()
+                .unwrap()
+                .local_addr()
+                .unwrap();
+            let mut extensions = Extensions::new();
+            extensions.insert(ConnectInfo(unnamed));
+            // A sidecar on the socket must not pass for a loopback peer
+            assert_eq!(peer_ip(&extensions), None);
+        }
+    }
+}
@@ -1,8 +1,19 @@
 use crate::config::{MatchType, RouteConfig};
+use crate::server::query::matches_predicates;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
 use regex::Regex;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::{collections::HashMap, path::PathBuf};
 use tracing::debug;
 
+// Params come from the raw request path, so existing escapes are kept as they are
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'%');
+
 #[derive(Debug, Clone)]
 pub struct RouteMatch {
     pub route: RouteConfig,
@@ -13,6 +24,38 @@ pub struct RouteMatcher {
     routes: Vec<CompiledRoute>,
 }
 
+/// Compiled form of a route, as reported by the admin API
+#[derive(Debug, Serialize)]
+pub struct RouteSummary {
+    pub index: usize,
+    pub path: String,
+    pub match_type: MatchType,
+    pub target: String,
+    pub methods: Vec<String>,
+    pub pattern: Option<String>,
+    pub params: Vec<String>,
+    pub source: Option<PathBuf>,
+}
+
+/// Whether a route matched a request, as reported by explain mode
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchOutcome {
+    Matched,
+    PathMismatch,
+    QueryMismatch,
+    ListenerMismatch,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteEvaluation {
+    pub index: usize,
+    pub path: String,
+    pub match_type: MatchType,
+    pub outcome: MatchOutcome,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone)]
 struct CompiledRoute {
     config: RouteConfig,
@@ -41,10 +84,11 @@ impl RouteMatcher {
                 }
                 MatchType::Regex => {
                     let regex = Regex::new(&route.path)?;
+                    let params = regex_param_names(&regex);
                     CompiledRoute {
                         config: route,
                         regex: Some(regex),
-                        param_names: Vec::new(),
+                        param_names: params,
                     }
                 }
                 MatchType::Prefix => CompiledRoute {
@@ -61,22 +105,145 @@ impl RouteMatcher {
         })
     }
 
-    pub fn find_match(&self, path: &str) -> Option<RouteMatch> {
-        for route in &self.routes {
+    pub fn find_match(&self, path: &str, query: Option<&str>) -> Option<RouteMatch> {
+        self.find_indexed(path, query)
+            .map(|(_, route_match)| route_match)
+    }
+
+    /// Like `find_match`, skipping routes not served on the listener
+    pub fn find_match_on(
+        &self,
+        listener: Option<&str>,
+        path: &str,
+        query: Option<&str>,
+    ) -> Option<RouteMatch> {
+        self.find_indexed_on(listener, path, query)
+            .map(|(_, route_match)| route_match)
+    }
+
+    /// Like `find_match`, also returning the position of the matched route
+    pub fn find_indexed(&self, path: &str, query: Option<&str>) -> Option<(usize, RouteMatch)> {
+        self.find_where(path, query, |_| true)
+    }
+
+    /// Like `find_indexed`, skipping routes not served on the listener
+    pub fn find_indexed_on(
+        &self,
+        listener: Option<&str>,
+        path: &str,
+        query: Option<&str>,
+    ) -> Option<(usize, RouteMatch)> {
+        self.find_where(path, query, |route| {
+            listener.is_none_or(|name| route.config.serves(name))
+        })
+    }
+
+    fn find_where(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        eligible: impl Fn(&CompiledRoute) -> bool,
+    ) -> Option<(usize, RouteMatch)> {
+        for (index, route) in self.routes.iter().enumerate() {
+            if !eligible(route) {
+                continue;
+            }
             if let Some(params) = self.matches_route(route, path) {
+                if !matches_predicates(&route.config.query.predicates, query) {
+                    continue;
+                }
                 debug!(
                     "Route matched: {} -> {} (type: {:?})",
-                    route.config.path, route.config.target, route.config.match_type
+                    route.config.path,
+                    route.config.upstream_target(),
+                    route.config.match_type
                 );
-                return Some(RouteMatch {
-                    route: route.config.clone(),
-                    params,
-                });
+                return Some((
+                    index,
+                    RouteMatch {
+                        route: route.config.clone(),
+                        params,
+                    },
+                ));
             }
         }
         None
     }
 
+    /// Every route tried for a request, in order, up to and including the match
+    pub fn evaluate(
+        &self,
+        listener: Option<&str>,
+        path: &str,
+        query: Option<&str>,
+    ) -> Vec<RouteEvaluation> {
+        let mut evaluations = Vec::new();
+
+        for (index, route) in self.routes.iter().enumerate() {
+            let pattern = route
+                .regex
+                .as_ref()
+                .map_or(route.config.path.as_str(), |regex| regex.as_str());
+            let (outcome, reason) = if let Some(name) =
+                listener.filter(|name| !route.config.serves(name))
+            {
+                let reason = format!("not served on listener {:?}", name);
+                (MatchOutcome::ListenerMismatch, reason)
+            } else if self.matches_route(route, path).is_none() {
+                let reason = match route.config.match_type {
+                    MatchType::Exact => format!("path is not {:?}", exact_path(&route.config.path)),
+                    MatchType::Prefix => format!("path does not start with {:?}", pattern),
+                    MatchType::Wildcard | MatchType::Regex => {
+                        format!("path does not match {:?}", pattern)
+                    }
+                };
+                (MatchOutcome::PathMismatch, reason)
+            } else if !matches_predicates(&route.config.query.predicates, query) {
+                let reason = "query predicates are not satisfied".to_string();
+                (MatchOutcome::QueryMismatch, reason)
+            } else {
+                (MatchOutcome::Matched, format!("path matches {:?}", pattern))
+            };
+
+            evaluations.push(RouteEvaluation {
+                index,
+                path: route.config.path.clone(),
+                match_type: route.config.match_type.clone(),
+                outcome,
+                reason,
+            });
+            if outcome == MatchOutcome::Matched {
+                break;
+            }
+        }
+
+        evaluations
+    }
+
+    pub fn routes(&self) -> Vec<RouteConfig> {
+        self.routes
+            .iter()
+            .map(|route| route.config.clone())
+            .collect()
+    }
+
+    pub fn summaries(&self) -> Vec<RouteSummary> {
+        self.routes
+            .iter()
+            .enumerate()
+            .map(|(index, route)| RouteSummary {
+                index,
+                path: route.config.path.clone(),
+                match_type: route.config.match_type.clone(),
+                target: route.config.upstream_target().to_string(),
+                methods: route.config.methods.clone(),
+                pattern: route.regex.as_ref().map(|regex| regex.as_str().to_string()),
+                params: route.param_names.clone(),
+                source: route.config.source.clone(),
+            })
+            .collect()
+    }
+
     fn matches_route(&self, route: &CompiledRoute, path: &str) -> Option<HashMap<String, String>> {
         match route.config.match_type {
             MatchType::Exact => {
@@ -124,6 +291,64 @@ impl RouteMatcher {
     }
 }
 
+impl RouteMatch {
+    /// Substitute `{name}` placeholders in a URL template with the extracted
+    /// params, each encoded as a path segment. Unknown placeholders are left untouched.
+    pub fn render(&self, template: &str) -> String {
+        self.render_with(template, |value| {
+            utf8_percent_encode(value, PATH_SEGMENT).to_string()
+        })
+    }
+
+    /// Like `render`, inserting the values as they are for callers that
+    /// encode them themselves, such as query strings
+    pub fn render_raw(&self, template: &str) -> String {
+        self.render_with(template, str::to_string)
+    }
+
+    // One pass over the template, so substituted values are never expanded again
+    fn render_with(&self, template: &str, encode: impl Fn(&str) -> String) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let param = after
+                .find('}')
+                .and_then(|end| Some((end, self.params.get(&after[..end])?)));
+            match param {
+                Some((end, value)) => {
+                    rendered.push_str(&encode(value));
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    rendered.push('{');
+                    rest = after;
+                }
+            }
+        }
+
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+// An empty exact path matches the root
+fn exact_path(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
+
+// Named groups keep their name, unnamed groups are exposed by position ("1", "2", ...)
+fn regex_param_names(regex: &Regex) -> Vec<String> {
+    regex
+        .capture_names()
+        .enumerate()
+        .skip(1)
+        .map(|(i, name)| name.map_or_else(|| i.to_string(), str::to_string))
+        .collect()
+}
+
 fn compile_wildcard_pattern(pattern: &str) -> Result<(Regex, Vec<String>), regex::Error> {
     let mut regex_pattern = String::new();
     let mut param_names = Vec::new();
@@ -146,7 +371,7 @@ fn compile_wildcard_pattern(pattern: &str) -> Result<(Regex, Vec<String>), regex
             '{' => {
                 // Extract parameter name
                 let mut param_name = String::new();
-                while let Some(ch) = chars.next() {
+                for ch in chars.by_ref() {
                     if ch == '}' {
                         break;
                     }
@@ -190,8 +415,8 @@ mod tests {
             path: path.to_string(),
             target: "http://example.com".to_string(),
             methods: vec!["GET".to_string()],
-            auth: Default::default(),
             match_type,
+            ..Default::default()
         }
     }
 
@@ -200,9 +425,9 @@ mod tests {
         let routes = vec![create_route("/api/v1", MatchType::Exact)];
         let matcher = RouteMatcher::new(routes).unwrap();
 
-        assert!(matcher.find_match("/api/v1").is_some());
-        assert!(matcher.find_match("/api/v2").is_none());
-        assert!(matcher.find_match("/api/v1/users").is_none());
+        assert!(matcher.find_match("/api/v1", None).is_some());
+        assert!(matcher.find_match("/api/v2", None).is_none());
+        assert!(matcher.find_match("/api/v1/users", None).is_none());
     }
 
     #[test]
@@ -214,14 +439,21 @@ mod tests {
         let matcher = RouteMatcher::new(routes).unwrap();
 
         // Single wildcard
-        assert!(matcher.find_match("/api/v1/users").is_some());
-        assert!(matcher.find_match("/api/v2/users").is_some());
-        assert!(matcher.find_match("/api/users").is_none()); // * must match something
+        assert!(matcher.find_match("/api/v1/users", None).is_some());
+        assert!(matcher.find_match("/api/v2/users", None).is_some());
+        assert!(matcher.find_match("/api/users", None).is_none()); // * must match something
 
         // Double wildcard
-        assert!(matcher.find_match("/files/docs/readme.txt").is_some());
-        assert!(matcher.find_match("/files/").is_some());
-        assert!(matcher.find_match("/files").is_some());
+        assert!(matcher.find_match("/files/docs/readme.txt", None).is_some());
+        assert!(matcher.find_match("/files/", None).is_some());
+        // The slash before ** is literal, so the bare prefix does not match
+        assert!(matcher.find_match("/files", None).is_none());
+
+        // The slashes around ** are literal in the middle of a pattern too
+        let routes = vec![create_route("/files/**/raw", MatchType::Wildcard)];
+        let matcher = RouteMatcher::new(routes).unwrap();
+        assert!(matcher.find_match("/files/a/b/raw", None).is_some());
+        assert!(matcher.find_match("/files/raw", None).is_none());
     }
 
     #[test]
@@ -232,7 +464,7 @@ mod tests {
         )];
         let matcher = RouteMatcher::new(routes).unwrap();
 
-        if let Some(route_match) = matcher.find_match("/users/123/posts/456") {
+        if let Some(route_match) = matcher.find_match("/users/123/posts/456", None) {
             assert_eq!(route_match.params.get("id"), Some(&"123".to_string()));
             assert_eq!(route_match.params.get("post_id"), Some(&"456".to_string()));
         } else {
@@ -245,10 +477,10 @@ mod tests {
         let routes = vec![create_route("/api", MatchType::Prefix)];
         let matcher = RouteMatcher::new(routes).unwrap();
 
-        assert!(matcher.find_match("/api").is_some());
-        assert!(matcher.find_match("/api/v1").is_some());
-        assert!(matcher.find_match("/api/v1/users").is_some());
-        assert!(matcher.find_match("/different").is_none());
+        assert!(matcher.find_match("/api", None).is_some());
+        assert!(matcher.find_match("/api/v1", None).is_some());
+        assert!(matcher.find_match("/api/v1/users", None).is_some());
+        assert!(matcher.find_match("/different", None).is_none());
     }
 
     #[test]
@@ -256,10 +488,101 @@ mod tests {
         let routes = vec![create_route(r"^/api/v\d+/users$", MatchType::Regex)];
         let matcher = RouteMatcher::new(routes).unwrap();
 
-        assert!(matcher.find_match("/api/v1/users").is_some());
-        assert!(matcher.find_match("/api/v2/users").is_some());
-        assert!(matcher.find_match("/api/v10/users").is_some());
-        assert!(matcher.find_match("/api/vX/users").is_none());
-        assert!(matcher.find_match("/api/v1/users/123").is_none());
+        assert!(matcher.find_match("/api/v1/users", None).is_some());
+        assert!(matcher.find_match("/api/v2/users", None).is_some());
+        assert!(matcher.find_match("/api/v10/users", None).is_some());
+        assert!(matcher.find_match("/api/vX/users", None).is_none());
+        assert!(matcher.find_match("/api/v1/users/123", None).is_none());
+    }
+
+    #[test]
+    fn test_regex_capture_groups() {
+        let routes = vec![create_route(
+            r"^/api/v(?P<version>\d+)/users/(\d+)$",
+            MatchType::Regex,
+        )];
+        let matcher = RouteMatcher::new(routes).unwrap();
+
+        let route_match = matcher.find_match("/api/v2/users/42", None).unwrap();
+        assert_eq!(route_match.params.get("version"), Some(&"2".to_string()));
+        assert_eq!(route_match.params.get("2"), Some(&"42".to_string()));
+    }
+
+    #[test]
+    fn test_query_predicates() {
+        let mut versioned = create_route("/api", MatchType::Exact);
+        versioned.query.predicates = vec![crate::config::QueryPredicate {
+            name: "version".to_string(),
+            value: Some("2".to_string()),
+            absent: false,
+        }];
+        versioned.target = "http://v2.example.com".to_string();
+        let routes = vec![versioned, create_route("/api", MatchType::Exact)];
+        let matcher = RouteMatcher::new(routes).unwrap();
+
+        let route_match = matcher.find_match("/api", Some("version=2")).unwrap();
+        assert_eq!(route_match.route.target, "http://v2.example.com");
+
+        let route_match = matcher.find_match("/api", Some("version=1")).unwrap();
+        assert_eq!(route_match.route.target, "http://example.com");
+    }
+
+    #[test]
+    fn test_listener_routes() {
+        let mut internal = create_route("/api", MatchType::Exact);
+        internal.listeners = vec!["internal".to_string()];
+        internal.target = "http://internal.example.com".to_string();
+        let routes = vec![internal, create_route("/api", MatchType::Exact)];
+        let matcher = RouteMatcher::new(routes).unwrap();
+
+        let route_match = matcher
+            .find_match_on(Some("internal"), "/api", None)
+            .unwrap();
+        assert_eq!(route_match.route.target, "http://internal.example.com");
+
+        let route_match = matcher.find_match_on(Some("public"), "/api", None).unwrap();
+        assert_eq!(route_match.route.target, "http://example.com");
+    }
+
+    #[test]
+    fn test_first_path_match_wins() {
+        // Methods are checked after matching, a mismatch does not fall through
+        let mut admin = create_route("/admin", MatchType::Exact);
+        admin.methods = vec!["GET".to_string()];
+        let mut catch_all = create_route("/", MatchType::Prefix);
+        catch_all.methods = Vec::new();
+        let matcher = RouteMatcher::new(vec![admin, catch_all]).unwrap();
+
+        let (index, _) = matcher.find_indexed_on(None, "/admin", None).unwrap();
+        assert_eq!(index, 0);
+    }
+
+    #[test]
+    fn test_render_template() {
+        let routes = vec![create_route("/users/{id}", MatchType::Wildcard)];
+        let matcher = RouteMatcher::new(routes).unwrap();
+
+        let route_match = matcher.find_match("/users/7", None).unwrap();
+        assert_eq!(
+            route_match.render("http://users-{id}.internal/{missing}"),
+            "http://users-7.internal/{missing}"
+        );
+    }
+
+    #[test]
+    fn test_render_encodes_values() {
+        let routes = vec![create_route(
+            r"^/raw/(?P<a>[^/]*)/(?P<b>.*)$",
+            MatchType::Regex,
+        )];
+        let matcher = RouteMatcher::new(routes).unwrap();
+
+        // A captured "{b}" is not expanded again and separators are escaped
+        let route_match = matcher.find_match("/raw/{b}/x/..%2F?y", None).unwrap();
+        assert_eq!(
+            route_match.render("http://up/{a}/{b}"),
+            "http://up/%7Bb%7D/x%2F..%2F%3Fy"
+        );
+        assert_eq!(route_match.render_raw("{a}"), "{b}");
     }
 }
@@ -0,0 +1,311 @@
+use crate::server::{
+    access_log::{MatchedRoute, UpstreamLatency},
+    error::ServerError,
+};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    serve::Listener,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tracing::error;
+
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    upstream_duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    in_flight: IntGauge,
+    open_connections: IntGauge,
+    config_reloads: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("sag".to_string()), None)?;
+
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Requests handled by the gateway"),
+            &["route", "method", "status"],
+        )?;
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Total request latency as seen by the gateway",
+            ),
+            &["route", "method", "status"],
+        )?;
+        let upstream_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "upstream_request_duration_seconds",
+                "Latency of upstream requests",
+            ),
+            &["upstream"],
+        )?;
+        let upstream_errors = IntCounterVec::new(
+            Opts::new("upstream_errors_total", "Upstream requests that failed"),
+            &["upstream"],
+        )?;
+        let in_flight = IntGauge::new("http_requests_in_flight", "Requests being handled")?;
+        let open_connections =
+            IntGauge::new("open_connections", "Client connections currently open")?;
+        let config_reloads = IntCounterVec::new(
+            Opts::new("config_reloads_total", "Configuration reload attempts"),
+            &["result"],
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(request_duration.clone()))?;
+        registry.register(Box::new(upstream_duration.clone()))?;
+        registry.register(Box::new(upstream_errors.clone()))?;
+        registry.register(Box::new(in_flight.clone()))?;
+        registry.register(Box::new(open_connections.clone()))?;
+        registry.register(Box::new(config_reloads.clone()))?;
+
+        Ok(Self {
+            registry,
+            requests,
+            request_duration,
+            upstream_duration,
+            upstream_errors,
+            in_flight,
+            open_connections,
+            config_reloads,
+        })
+    }
+
+    /// Record the outcome of a call to an upstream, named or given by its target
+    pub fn observe_upstream(&self, upstream: &str, result: &Result<Response<Body>, ServerError>) {
+        match result {
+            Ok(response) => {
+                if let Some(UpstreamLatency(latency)) = response.extensions().get() {
+                    self.upstream_duration
+                        .with_label_values(&[upstream])
+                        .observe(latency.as_secs_f64());
+                }
+            }
+            Err(ServerError::ProxyError(_)) => {
+                self.upstream_errors.with_label_values(&[upstream]).inc();
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Gauge of requests being handled, kept up to date by the shutdown tracker
+    pub fn in_flight_gauge(&self) -> IntGauge {
+        self.in_flight.clone()
+    }
+
+    pub fn record_reload(&self, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.config_reloads.with_label_values(&[result]).inc();
+    }
+
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+pub async fn metrics_middleware(
+    State(metrics): State<Arc<Metrics>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+
+    let response = next.run(request).await;
+
+    let route = response
+        .extensions()
+        .get::<MatchedRoute>()
+        .map_or(UNMATCHED_ROUTE, |m| m.path.as_str());
+    let status = status_class(response.status());
+    let labels = [route, method.as_str(), status];
+
+    metrics.requests.with_label_values(&labels).inc();
+    metrics
+        .request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+
+    response
+}
+
+pub async fn metrics_handler(State(metrics): State<Arc<Metrics>>) -> Response {
+    match metrics.render() {
+        Ok(body) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response(),
+        Err(e) => {
+            error!("Failed to encode metrics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Listener wrapper keeping the open connections gauge up to date
+pub struct TrackedListener<L> {
+    inner: L,
+    open_connections: IntGauge,
+}
+
+impl<L> TrackedListener<L> {
+    pub fn new(inner: L, metrics: &Metrics) -> Self {
+        Self {
+            inner,
+            open_connections: metrics.open_connections.clone(),
+        }
+    }
+}
+
+impl<L: Listener> Listener for TrackedListener<L> {
+    type Io = TrackedIo<L::Io>;
+    type Addr = L::Addr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let (io, addr) = self.inner.accept().await;
+        self.open_connections.inc();
+        let io = TrackedIo {
+            inner: io,
+            open_connections: self.open_connections.clone(),
+        };
+        (io, addr)
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+pub struct TrackedIo<T> {
+    inner: T,
+    open_connections: IntGauge,
+}
+
+impl<T> Drop for TrackedIo<T> {
+    fn drop(&mut self) {
+        self.open_connections.dec();
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for TrackedIo<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for TrackedIo<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, middleware, routing::get};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_status_class() {
+        assert_eq!(status_class(StatusCode::OK), "2xx");
+        assert_eq!(status_class(StatusCode::NOT_FOUND), "4xx");
+        assert_eq!(status_class(StatusCode::BAD_GATEWAY), "5xx");
+    }
+
+    #[tokio::test]
+    async fn test_request_metrics() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let app = Router::new()
+            .route(
+                "/users",
+                get(|| async {
+                    let mut response = "ok".into_response();
+                    response.extensions_mut().insert(MatchedRoute {
+                        path: "/users".to_string(),
+                        target: "http://users".to_string(),
+                    });
+                    response
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                metrics.clone(),
+                metrics_middleware,
+            ));
+
+        for uri in ["/users", "/missing"] {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(request).await.unwrap();
+        }
+        metrics.observe_upstream(
+            "users",
+            &Err(ServerError::ProxyError("refused".to_string())),
+        );
+
+        let output = metrics.render().unwrap();
+        assert!(
+            output.contains(
+                "sag_http_requests_total{method=\"GET\",route=\"/users\",status=\"2xx\"} 1"
+            )
+        );
+        assert!(output.contains(
+            "sag_http_requests_total{method=\"GET\",route=\"unmatched\",status=\"4xx\"} 1"
+        ));
+        assert!(output.contains("sag_upstream_errors_total{upstream=\"users\"} 1"));
+        assert!(output.contains("sag_http_requests_in_flight 0"));
+    }
+}
@@ -1,74 +1,406 @@
+pub mod access_log;
+pub mod actions;
+pub mod admin;
+pub mod body;
+pub mod cache;
+pub mod compression;
+pub mod cors;
 pub mod error;
+pub mod error_pages;
+pub mod explain;
+pub mod health;
+pub mod listener;
 pub mod matcher;
+pub mod metrics;
 pub mod proxy;
+pub mod query;
+pub mod request_id;
+pub mod route_table;
 pub mod routes;
+pub mod shutdown;
+pub mod tls;
+pub mod trace_context;
+pub mod upstreams;
 
 use crate::config::AppConfig;
+use crate::logging::access::AccessLogger;
+use access_log::access_log_middleware;
+use admin::{AdminState, admin_router, redact_config};
 use anyhow::Result;
 use axum::{
-    Router,
+    Extension, Router, middleware,
     routing::{any, get},
+    serve::{Listener, ListenerExt},
 };
+use cache::ResponseCache;
+use health::{Health, health_router};
+use listener::{BoundListener, ListenerName, bind};
+use listenfd::ListenFd;
 use matcher::RouteMatcher;
-use routes::{AppState, handle_request, health_check};
-use std::{net::SocketAddr, sync::Arc};
-use tokio::net::TcpListener;
+use metrics::{Metrics, TrackedListener, metrics_handler, metrics_middleware};
+use request_id::{RequestIds, request_id_middleware};
+use route_table::RouteTable;
+use routes::{AppState, handle_request};
+use shutdown::{DrainSettings, InFlight, Shutdown, in_flight_middleware, serve_until_signal};
+use std::{
+    collections::HashMap, fmt::Debug, io, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration,
+};
+use tls::TlsListener;
+use tokio::{net::TcpListener, task::JoinSet};
 use tracing::{error, info};
+use upstreams::{UpstreamHealth, spawn_health_checks};
 
-pub async fn start_server(config: AppConfig) -> Result<()> {
-    let addr = SocketAddr::new(config.server.host, config.server.port);
-
-    info!("Starting server on {}", addr);
+pub async fn start_server(config: AppConfig, config_path: Option<PathBuf>) -> Result<()> {
+    info!("Starting server");
     info!("Configured routes: {}", config.routes.len());
 
     // Log configured routes
     for (i, route) in config.routes.iter().enumerate() {
         info!(
-            "Route {}: {} -> {} (methods: {:?}, type: {:?})",
+            "Route {}: {} -> {} (methods: {:?}, type: {:?}, from: {})",
             i,
             if route.path.is_empty() {
                 "/"
             } else {
                 &route.path
             },
-            route.target,
+            route.upstream_target(),
             route.methods,
-            route.match_type
+            route.match_type,
+            route
+                .source
+                .as_deref()
+                .map_or_else(|| "-".into(), |path| path.display().to_string())
         );
     }
 
+    // Snapshot before the config is taken apart below
+    let config_snapshot = if config.admin.enabled {
+        Some(redact_config(serde_json::to_value(&config)?))
+    } else {
+        None
+    };
+
     // Create route matcher
     let matcher = RouteMatcher::new(config.routes).map_err(|e| {
         error!("Failed to create route matcher: {}", e);
         anyhow::anyhow!("Route matcher creation failed: {}", e)
     })?;
 
+    let request_ids = RequestIds::new(config.request_id).map_err(|e| {
+        error!("Invalid request ID header: {}", e);
+        anyhow::anyhow!("Invalid request ID header: {}", e)
+    })?;
+
+    let explainer = if config.explain.enabled {
+        let explainer = explain::Explainer::new(&config.explain).map_err(|e| {
+            error!("Invalid explain header: {}", e);
+            anyhow::anyhow!("Invalid explain header: {}", e)
+        })?;
+        Some(Arc::new(explainer))
+    } else {
+        None
+    };
+
+    let access_logger = if config.logging.access.enabled {
+        let logger = AccessLogger::new(&config.logging.access).map_err(|e| {
+            error!("Failed to open access log: {}", e);
+            anyhow::anyhow!("Failed to open access log: {}", e)
+        })?;
+        Some(Arc::new(logger))
+    } else {
+        None
+    };
+
+    let metrics = if config.metrics.enabled {
+        let metrics = Metrics::new().map_err(|e| {
+            error!("Failed to create metrics registry: {}", e);
+            anyhow::anyhow!("Failed to create metrics registry: {}", e)
+        })?;
+        Some(Arc::new(metrics))
+    } else {
+        None
+    };
+
+    let mut upstream_clients = HashMap::new();
+    for (name, upstream) in &config.upstreams {
+        let client = proxy::ProxyClient::for_upstream(upstream).map_err(|e| {
+            error!("Failed to create client for upstream {}: {}", name, e);
+            anyhow::anyhow!("Failed to create client for upstream {}: {}", name, e)
+        })?;
+        upstream_clients.insert(name.clone(), Arc::new(client));
+    }
+
+    let upstreams = Arc::new(UpstreamHealth::new());
+    spawn_health_checks(&config.upstreams, &upstream_clients, &upstreams);
+    let routes = Arc::new(RouteTable::new(matcher).with_upstreams(config.upstreams));
+    let health = Arc::new(Health::new(
+        Duration::from_secs(config.health.warmup_secs),
+        routes.clone(),
+        upstreams.clone(),
+    ));
+
+    if config.health.enabled && config.health.on_admin && !config.admin.enabled {
+        error!("health.on_admin requires the admin API to be enabled");
+        return Err(anyhow::anyhow!(
+            "health.on_admin requires the admin API to be enabled"
+        ));
+    }
+
+    let metrics_on_admin =
+        config.metrics.enabled && config.metrics.listen.is_none() && !config.metrics.public;
+    if metrics_on_admin && !config.admin.enabled {
+        error!("metrics need metrics.listen, metrics.public or the admin API");
+        return Err(anyhow::anyhow!(
+            "metrics need metrics.listen, metrics.public or the admin API"
+        ));
+    }
+
     // Create shared state
     let state = AppState {
-        matcher: Arc::new(matcher),
+        routes: routes.clone(),
+        upstreams: upstreams.clone(),
         proxy_client: Arc::new(proxy::ProxyClient::new()),
+        upstream_clients: Arc::new(upstream_clients),
+        cache: config
+            .cache
+            .enabled
+            .then(|| Arc::new(ResponseCache::new(&config.cache))),
+        compression: Arc::new(config.compression),
+        cors: Arc::new(config.cors),
+        metrics: metrics.clone(),
+        explainer,
+        error_pages: Arc::new(config.errors),
         debug: config.debug,
     };
 
+    // Servers stop accepting once shutdown is triggered
+    let shutdown = Shutdown::new();
+    let mut servers = JoinSet::new();
+
     // Build the router
-    let app = Router::new()
-        .route("/health", get(health_check))
-        .fallback(any(handle_request))
-        .with_state(state);
+    let mut app: Router<AppState> = Router::new();
+
+    if config.health.enabled && !config.health.on_admin {
+        app = app.merge(health_router(&config.health, health.clone()));
+    }
+
+    if let Some(metrics) = &metrics {
+        let path = &config.metrics.path;
+        match config.metrics.listen {
+            Some(metrics_addr) => {
+                spawn_listener(
+                    &mut servers,
+                    &shutdown,
+                    "Metrics",
+                    metrics_addr,
+                    metrics_router(path, metrics.clone()),
+                )
+                .await?
+            }
+            None if config.metrics.public => {
+                info!("Serving metrics on {}", path);
+                app = app.merge(metrics_router(path, metrics.clone()));
+            }
+            None => info!("Serving metrics on the admin API at {}", path),
+        }
+    }
+
+    if let Some(config_snapshot) = config_snapshot {
+        let token = config.admin.token.clone().ok_or_else(|| {
+            error!("The admin API requires admin.token to be set");
+            anyhow::anyhow!("admin.token is required when the admin API is enabled")
+        })?;
+        let listeners = config
+            .server
+            .effective_listeners()
+            .iter()
+            .map(|listener| listener.name())
+            .collect();
+        let admin_state = AdminState {
+            routes,
+            upstreams,
+            metrics: metrics.clone(),
+            health: health.clone(),
+            config: config_snapshot,
+            listeners,
+            config_path,
+            token,
+            metrics_path: metrics_on_admin.then(|| config.metrics.path.clone()),
+        };
+        let mut admin = admin_router(Arc::new(admin_state));
+        // Probes stay unauthenticated so orchestrators can reach them
+        if config.health.enabled && config.health.on_admin {
+            admin = admin.merge(health_router(&config.health, health.clone()));
+        }
+        spawn_listener(
+            &mut servers,
+            &shutdown,
+            "Admin API",
+            config.admin.listen,
+            admin,
+        )
+        .await?;
+    }
+
+    let mut app = app.fallback(any(handle_request)).with_state(state);
+
+    if let Some(metrics) = &metrics {
+        app = app.layer(middleware::from_fn_with_state(
+            metrics.clone(),
+            metrics_middleware,
+        ));
+    }
+
+    // Access logging runs inside the request ID scope
+    if let Some(logger) = access_logger {
+        app = app.layer(middleware::from_fn_with_state(
+            logger,
+            access_log_middleware,
+        ));
+    }
+
+    let in_flight = Arc::new(InFlight::new(
+        metrics.as_ref().map(|metrics| metrics.in_flight_gauge()),
+    ));
+    let app = app
+        .layer(middleware::from_fn_with_state(
+            Arc::new(request_ids),
+            request_id_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            in_flight.clone(),
+            in_flight_middleware,
+        ));
+
+    // Bind every listener before serving, so a bad address fails startup
+    let mut fds = ListenFd::from_env();
+    let mut bound = Vec::new();
+    for listener in config.server.effective_listeners() {
+        let name = listener.name();
+        let socket = bind(&listener.address, &mut fds).await.map_err(|e| {
+            error!(
+                "Failed to bind listener {} to {}: {}",
+                name, listener.address, e
+            );
+            e
+        })?;
+        let acceptor = listener.tls.as_ref().map(tls::acceptor).transpose()?;
+        info!(
+            "Listener {} on {}{}",
+            name,
+            listener.address,
+            if acceptor.is_some() { " (TLS)" } else { "" }
+        );
+        bound.push((name, socket, acceptor));
+    }
+
+    // Start the servers
+    for (name, socket, acceptor) in bound {
+        let app = app.clone().layer(Extension(ListenerName(name.into())));
+        let metrics = metrics.as_deref();
+        match (socket, acceptor) {
+            (BoundListener::Tcp(listener), None) => {
+                serve_on(&mut servers, listener, app, metrics, &shutdown)
+            }
+            (BoundListener::Tcp(listener), Some(acceptor)) => serve_on(
+                &mut servers,
+                TlsListener::new(listener, acceptor)?,
+                app,
+                metrics,
+                &shutdown,
+            ),
+            #[cfg(unix)]
+            (BoundListener::Unix(listener), None) => {
+                serve_on(&mut servers, listener, app, metrics, &shutdown)
+            }
+            #[cfg(unix)]
+            (BoundListener::Unix(listener), Some(acceptor)) => serve_on(
+                &mut servers,
+                TlsListener::new(listener, acceptor)?,
+                app,
+                metrics,
+                &shutdown,
+            ),
+        }
+    }
+
+    // One listener failing stops the whole server
+    let server = tokio::spawn(async move {
+        while let Some(result) = servers.join_next().await {
+            result.map_err(io::Error::other)??;
+        }
+        Ok(())
+    });
+
+    let settings = DrainSettings {
+        delay: Duration::from_secs(config.server.shutdown_delay_secs),
+        timeout: Duration::from_secs(config.server.drain_timeout_secs),
+    };
+    serve_until_signal(server, shutdown, health, in_flight, settings).await
+}
+
+// Serve the app on one listener, counting connections when metrics are enabled
+fn serve_on<L>(
+    servers: &mut JoinSet<io::Result<()>>,
+    listener: L,
+    app: Router,
+    metrics: Option<&Metrics>,
+    shutdown: &Shutdown,
+) where
+    L: Listener,
+    L::Addr: Clone + Debug + Sync + 'static,
+{
+    // TapIo provides ConnectInfo with the listener's peer address type
+    match metrics {
+        Some(metrics) => {
+            let listener = TrackedListener::new(listener, metrics).tap_io(|_| {});
+            let serve = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<L::Addr>(),
+            )
+            .with_graceful_shutdown(shutdown.triggered());
+            servers.spawn(serve.into_future());
+        }
+        None => {
+            let serve = axum::serve(
+                listener.tap_io(|_| {}),
+                app.into_make_service_with_connect_info::<L::Addr>(),
+            )
+            .with_graceful_shutdown(shutdown.triggered());
+            servers.spawn(serve.into_future());
+        }
+    }
+}
+
+fn metrics_router<S>(path: &str, metrics: Arc<Metrics>) -> Router<S> {
+    Router::new()
+        .route(path, get(metrics_handler))
+        .with_state(metrics)
+}
 
-    // Create listener
+// Serve an auxiliary router on its own port, draining it with the others
+async fn spawn_listener(
+    servers: &mut JoinSet<io::Result<()>>,
+    shutdown: &Shutdown,
+    name: &'static str,
+    addr: SocketAddr,
+    router: Router,
+) -> Result<()> {
     let listener = TcpListener::bind(&addr).await.map_err(|e| {
-        error!("Failed to bind to {}: {}", addr, e);
+        error!("Failed to bind {} listener to {}: {}", name, addr, e);
         e
     })?;
 
-    info!("Server listening on {}", addr);
+    info!("{} listening on {}", name, addr);
+    let serve = axum::serve(listener, router).with_graceful_shutdown(shutdown.triggered());
+    servers.spawn(async move {
+        serve
+            .await
+            .inspect_err(|e| error!("{} server error: {}", name, e))
+    });
 
-    // Start the server
-    axum::serve(listener, app).await.map_err(|e| {
-        error!("Server error: {}", e);
-        e.into()
-    })
+    Ok(())
 }
 
 #[cfg(test)]
@@ -83,16 +415,16 @@ mod tests {
                 host: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 port: 8080,
                 max_connections: 1024,
+                ..Default::default()
             },
             routes: vec![RouteConfig {
                 path: "/api/v1".to_string(),
                 target: "http://localhost:3000".to_string(),
                 methods: vec!["GET".to_string(), "POST".to_string()],
-                auth: Default::default(),
                 match_type: MatchType::Exact,
+                ..Default::default()
             }],
-            logging: Default::default(),
-            debug: false,
+            ..Default::default()
         }
     }
 
@@ -1,4 +1,6 @@
-use crate::server::error::ServerError;
+use crate::config::UpstreamConfig;
+use crate::server::{access_log::UpstreamLatency, error::ServerError, trace_context};
+use anyhow::Context;
 use axum::{
     body::Body,
     extract::Request,
@@ -6,7 +8,8 @@ use axum::{
     response::Response,
 };
 use reqwest::Client;
-use tracing::{debug, error};
+use std::time::{Duration, Instant};
+use tracing::{Instrument, debug, error, field, info_span};
 
 pub struct ProxyClient {
     client: Client,
@@ -22,6 +25,53 @@ impl ProxyClient {
         Self { client }
     }
 
+    /// Client with the timeouts, pool size and TLS settings of a named upstream
+    pub fn for_upstream(upstream: &UpstreamConfig) -> anyhow::Result<Self> {
+        let mut builder = Client::builder().redirect(reqwest::redirect::Policy::none());
+        if let Some(secs) = upstream.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = upstream.connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(max) = upstream.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(tls) = &upstream.tls {
+            if let Some(path) = &tls.ca_cert {
+                let pem = std::fs::read(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let certs = reqwest::Certificate::from_pem_bundle(&pem)
+                    .with_context(|| format!("Invalid CA certificate in {}", path.display()))?;
+                for cert in certs {
+                    builder = builder.add_root_certificate(cert);
+                }
+            }
+            builder = builder.danger_accept_invalid_certs(tls.insecure_skip_verify);
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+        })
+    }
+
+    /// GET a URL for a health check; statuses other than 2xx and 3xx fail it
+    pub async fn check(&self, url: &str, timeout: Duration) -> Result<(), String> {
+        let response = self
+            .client
+            .get(url)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if status.is_success() || status.is_redirection() {
+            Ok(())
+        } else {
+            Err(format!("returned {}", status))
+        }
+    }
+
     pub async fn proxy_request(
         &self,
         request: Request,
@@ -30,7 +80,7 @@ impl ProxyClient {
         let (parts, body) = request.into_parts();
 
         // Build target URL
-        let target_url = self.build_target_url(target_base, &parts.uri)?;
+        let target_url = build_target_url(target_base, &parts.uri)?;
         debug!("Proxying {} {} to {}", parts.method, parts.uri, target_url);
 
         // Convert axum body to bytes
@@ -45,7 +95,18 @@ impl ProxyClient {
         };
 
         // Prepare headers (filter out hop-by-hop headers and set correct Host)
-        let headers = self.filter_request_headers(&parts.headers, &target_url);
+        let mut headers = self.filter_request_headers(&parts.headers, &target_url);
+
+        // The upstream call is a child span of the gateway request
+        let span = info_span!(
+            "upstream",
+            otel.kind = "client",
+            http.request.method = %parts.method,
+            url.full = %target_url,
+            http.response.status_code = field::Empty,
+            otel.status_code = field::Empty,
+        );
+        trace_context::inject_trace(&span, &mut headers);
 
         // Build the proxied request
         let mut req_builder = self
@@ -58,42 +119,28 @@ impl ProxyClient {
         }
 
         // Execute the request
-        let response = match req_builder.send().await {
+        let started = Instant::now();
+        let response = match req_builder.send().instrument(span.clone()).await {
             Ok(resp) => {
+                span.record("http.response.status_code", resp.status().as_u16());
                 debug!("Proxy response status: {}", resp.status());
                 debug!("Proxy response headers: {:?}", resp.headers());
                 resp
             }
             Err(e) => {
+                span.record("otel.status_code", "error");
                 error!("Proxy request failed: {}", e);
                 return Err(ServerError::ProxyError(format!("Request failed: {}", e)));
             }
         };
 
         // Convert response back to axum format
-        self.convert_response(response).await
-    }
-
-    fn build_target_url(
-        &self,
-        target_base: &str,
-        original_uri: &Uri,
-    ) -> Result<String, ServerError> {
-        let target_base = target_base.trim_end_matches('/');
+        let mut response = self.convert_response(response).await?;
+        response
+            .extensions_mut()
+            .insert(UpstreamLatency(started.elapsed()));
 
-        let path_and_query = original_uri
-            .path_and_query()
-            .map(|pq| pq.as_str())
-            .unwrap_or("/");
-
-        let target_url = format!("{}{}", target_base, path_and_query);
-
-        // Basic URL validation
-        if !target_url.starts_with("http://") && !target_url.starts_with("https://") {
-            return Err(ServerError::InvalidTarget(target_url));
-        }
-
-        Ok(target_url)
+        Ok(response)
     }
 
     fn filter_request_headers(&self, headers: &HeaderMap, target_url: &str) -> HeaderMap {
@@ -155,17 +202,6 @@ impl ProxyClient {
         let status = response.status();
         let headers = response.headers().clone();
 
-        // Get response body
-        let body_bytes = match response.bytes().await {
-            Ok(bytes) => bytes,
-            Err(e) => {
-                error!("Failed to read response body: {}", e);
-                return Err(ServerError::ProxyError(
-                    "Failed to read response body".to_string(),
-                ));
-            }
-        };
-
         // Build axum response
         let mut response_builder = Response::builder().status(status);
 
@@ -181,7 +217,9 @@ impl ProxyClient {
             }
         }
 
-        let response = response_builder.body(Body::from(body_bytes)).map_err(|e| {
+        // The body is streamed through rather than buffered
+        let body = Body::from_stream(response.bytes_stream());
+        let response = response_builder.body(body).map_err(|e| {
             error!("Failed to build response: {}", e);
             ServerError::InternalError("Failed to build response".to_string())
         })?;
@@ -195,3 +233,22 @@ impl Default for ProxyClient {
         Self::new()
     }
 }
+
+/// Upstream URL for a request: the target base followed by the request path and query
+pub fn build_target_url(target_base: &str, original_uri: &Uri) -> Result<String, ServerError> {
+    let target_base = target_base.trim_end_matches('/');
+
+    let path_and_query = original_uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+
+    let target_url = format!("{}{}", target_base, path_and_query);
+
+    // Basic URL validation
+    if !target_url.starts_with("http://") && !target_url.starts_with("https://") {
+        return Err(ServerError::InvalidTarget(target_url));
+    }
+
+    Ok(target_url)
+}
@@ -0,0 +1,156 @@
+use crate::config::{QueryConfig, QueryPredicate};
+use crate::server::matcher::RouteMatch;
+use url::form_urlencoded;
+
+fn parse_query(query: Option<&str>) -> Vec<(String, String)> {
+    query
+        .map(|q| form_urlencoded::parse(q.as_bytes()).into_owned().collect())
+        .unwrap_or_default()
+}
+
+/// Check whether a raw query string satisfies all route predicates
+pub fn matches_predicates(predicates: &[QueryPredicate], query: Option<&str>) -> bool {
+    if predicates.is_empty() {
+        return true;
+    }
+
+    let pairs = parse_query(query);
+
+    predicates.iter().all(|predicate| {
+        let mut values = pairs
+            .iter()
+            .filter(|(name, _)| name == &predicate.name)
+            .map(|(_, value)| value);
+
+        if predicate.absent {
+            return values.next().is_none();
+        }
+
+        match &predicate.value {
+            Some(expected) => values.any(|v| v == expected),
+            None => values.next().is_some(),
+        }
+    })
+}
+
+/// Apply the route's query rules and return the query string to forward.
+/// Rules run in order: remove, rename, set, add. Values may use `{param}` templates.
+pub fn rewrite_query(
+    config: &QueryConfig,
+    query: Option<&str>,
+    route_match: &RouteMatch,
+) -> Option<String> {
+    let mut pairs = parse_query(query);
+
+    pairs.retain(|(name, _)| !config.remove.contains(name));
+
+    for (name, _) in pairs.iter_mut() {
+        if let Some(new_name) = config.rename.get(name) {
+            *name = new_name.clone();
+        }
+    }
+
+    for (name, value) in &config.set {
+        pairs.retain(|(n, _)| n != name);
+        pairs.push((name.clone(), route_match.render_raw(value)));
+    }
+
+    for (name, value) in &config.add {
+        if !pairs.iter().any(|(n, _)| n == name) {
+            pairs.push((name.clone(), route_match.render_raw(value)));
+        }
+    }
+
+    if pairs.is_empty() {
+        return None;
+    }
+
+    Some(
+        form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(pairs)
+            .finish(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RouteConfig;
+    use indexmap::IndexMap;
+    use std::collections::HashMap;
+
+    fn predicate(name: &str, value: Option<&str>, absent: bool) -> QueryPredicate {
+        QueryPredicate {
+            name: name.to_string(),
+            value: value.map(str::to_string),
+            absent,
+        }
+    }
+
+    #[test]
+    fn test_matches_predicates() {
+        let predicates = vec![
+            predicate("version", Some("2"), false),
+            predicate("debug", None, true),
+        ];
+
+        assert!(matches_predicates(&predicates, Some("version=2&x=1")));
+        assert!(!matches_predicates(&predicates, Some("version=1")));
+        assert!(!matches_predicates(&predicates, Some("version=2&debug=1")));
+        assert!(!matches_predicates(&predicates, None));
+        assert!(matches_predicates(&[], None));
+    }
+
+    #[test]
+    fn test_rewrite_query() {
+        let config = QueryConfig {
+            remove: vec!["access_token".to_string()],
+            rename: IndexMap::from([("q".to_string(), "search".to_string())]),
+            set: IndexMap::from([("user".to_string(), "{id}".to_string())]),
+            add: IndexMap::from([("api_version".to_string(), "2".to_string())]),
+            ..Default::default()
+        };
+        let route_match = RouteMatch {
+            route: RouteConfig::default(),
+            params: HashMap::from([("id".to_string(), "42".to_string())]),
+        };
+
+        let query = rewrite_query(
+            &config,
+            Some("q=rust&access_token=secret&user=1"),
+            &route_match,
+        );
+        assert_eq!(query.as_deref(), Some("search=rust&user=42&api_version=2"));
+
+        let config = QueryConfig {
+            remove: vec!["a".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(rewrite_query(&config, Some("a=1"), &route_match), None);
+    }
+
+    #[test]
+    fn test_rewrite_order() {
+        let pairs = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (name.to_string(), "1".to_string()))
+                .collect()
+        };
+        let config = QueryConfig {
+            set: pairs(&["z", "m", "a", "k"]),
+            add: pairs(&["y", "b", "x"]),
+            ..Default::default()
+        };
+        let route_match = RouteMatch {
+            route: RouteConfig::default(),
+            params: HashMap::new(),
+        };
+
+        // Cache keys rely on the same parameters always coming out in order
+        assert_eq!(
+            rewrite_query(&config, None, &route_match).as_deref(),
+            Some("z=1&m=1&a=1&k=1&y=1&b=1&x=1")
+        );
+    }
+}
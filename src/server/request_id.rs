@@ -0,0 +1,192 @@
+use crate::config::RequestIdConfig;
+use crate::server::{listener::peer_ip, trace_context};
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, header::InvalidHeaderName},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use tracing::{Instrument, debug, field, info_span};
+use uuid::Uuid;
+
+// Longer incoming IDs are replaced to keep logs and upstream headers sane
+const MAX_INCOMING_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request ID of the request being handled by the current task
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+pub struct RequestIds {
+    header: HeaderName,
+    config: RequestIdConfig,
+}
+
+impl RequestIds {
+    pub fn new(config: RequestIdConfig) -> Result<Self, InvalidHeaderName> {
+        Ok(Self {
+            header: HeaderName::try_from(config.header.as_str())?,
+            config,
+        })
+    }
+
+    fn resolve(&self, request: &Request) -> String {
+        if self.config.trust_incoming && self.is_trusted_peer(request) {
+            let incoming = request
+                .headers()
+                .get(&self.header)
+                .and_then(|v| v.to_str().ok())
+                .filter(|id| !id.is_empty() && id.len() <= MAX_INCOMING_LENGTH);
+
+            if let Some(id) = incoming {
+                return id.to_string();
+            }
+        }
+
+        Uuid::now_v7().to_string()
+    }
+
+    fn is_trusted_peer(&self, request: &Request) -> bool {
+        peer_ip(request.extensions()).is_some_and(|ip| {
+            self.config
+                .trusted_networks
+                .iter()
+                .any(|network| network.contains(&ip))
+        })
+    }
+}
+
+/// Assign a request ID, forward it upstream and echo it in the response
+pub async fn request_id_middleware(
+    State(request_ids): State<Arc<RequestIds>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let id = request_ids.resolve(&request);
+    let value = HeaderValue::try_from(id.as_str()).expect("request IDs are valid header values");
+
+    request
+        .headers_mut()
+        .insert(request_ids.header.clone(), value.clone());
+
+    let span = info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        otel.kind = "server",
+        http.route = field::Empty,
+        http.response.status_code = field::Empty,
+    );
+    trace_context::continue_trace(&span, request.headers());
+
+    let mut response = REQUEST_ID
+        .scope(id, async move {
+            debug!("Request ID assigned");
+            next.run(request).await
+        })
+        .instrument(span.clone())
+        .await;
+    span.record("http.response.status_code", response.status().as_u16());
+
+    response
+        .headers_mut()
+        .insert(request_ids.header.clone(), value);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::ConnectInfo};
+    use std::net::SocketAddr;
+
+    fn request_ids(trusted_networks: &[&str]) -> RequestIds {
+        RequestIds::new(RequestIdConfig {
+            trusted_networks: trusted_networks
+                .iter()
+                .map(|n| n.parse().unwrap())
+                .collect(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn request_from(peer: &str, id: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri("/");
+        if let Some(id) = id {
+            builder = builder.header("x-request-id", id);
+        }
+        let mut request = builder.body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        request
+    }
+
+    #[test]
+    fn test_generates_uuid_v7() {
+        let id = request_ids(&[]).resolve(&request_from("127.0.0.1:1000", None));
+        let uuid = Uuid::parse_str(&id).unwrap();
+        assert_eq!(uuid.get_version_num(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_error_body_includes_request_id() {
+        use crate::server::error::ServerError;
+        use axum::{Router, middleware, routing::get};
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { Err::<(), _>(ServerError::RouteNotFound) }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Arc::new(request_ids(&[])),
+                request_id_middleware,
+            ));
+
+        let request = Request::builder()
+            .uri("/")
+            .header("x-request-id", "req-1")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let id = response.headers()["x-request-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_ne!(id, "req-1");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], id);
+    }
+
+    #[test]
+    fn test_trusted_incoming() {
+        let ids = request_ids(&["10.0.0.0/8"]);
+
+        let id = ids.resolve(&request_from("10.1.2.3:1000", Some("abc-123")));
+        assert_eq!(id, "abc-123");
+
+        let id = ids.resolve(&request_from("192.168.1.1:1000", Some("abc-123")));
+        assert_ne!(id, "abc-123");
+
+        // Without trusted networks no peer may choose its ID
+        let id = request_ids(&[]).resolve(&request_from("10.1.2.3:1000", Some("abc-123")));
+        assert_ne!(id, "abc-123");
+
+        let too_long = "x".repeat(MAX_INCOMING_LENGTH + 1);
+        let id = ids.resolve(&request_from("10.1.2.3:1000", Some(&too_long)));
+        assert_ne!(id, too_long);
+    }
+}
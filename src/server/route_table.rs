@@ -0,0 +1,119 @@
+use crate::config::{RouteConfig, UpstreamConfig, upstream::resolve_upstream};
+use crate::server::matcher::RouteMatcher;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// The live set of routes. Requests take a snapshot of the current matcher,
+/// changes compile a new one and swap it in as a whole.
+pub struct RouteTable {
+    matcher: RwLock<Arc<RouteMatcher>>,
+    version: AtomicU64,
+    // Lets routes added at runtime reference upstreams by name
+    upstreams: HashMap<String, UpstreamConfig>,
+}
+
+impl RouteTable {
+    pub fn new(matcher: RouteMatcher) -> Self {
+        Self {
+            matcher: RwLock::new(Arc::new(matcher)),
+            version: AtomicU64::new(1),
+            upstreams: HashMap::new(),
+        }
+    }
+
+    pub fn with_upstreams(mut self, upstreams: HashMap<String, UpstreamConfig>) -> Self {
+        self.upstreams = upstreams;
+        self
+    }
+
+    pub fn upstreams(&self) -> &HashMap<String, UpstreamConfig> {
+        &self.upstreams
+    }
+
+    pub fn current(&self) -> Arc<RouteMatcher> {
+        self.matcher
+            .read()
+            .expect("route table lock poisoned")
+            .clone()
+    }
+
+    /// Bumped every time the routes change
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    /// Edit a copy of the routes; nothing changes if the edit or compilation fails
+    pub fn update<F>(&self, edit: F) -> Result<u64, String>
+    where
+        F: FnOnce(&mut Vec<RouteConfig>) -> Result<(), String>,
+    {
+        // Held for the whole edit so concurrent updates don't lose each other
+        let mut matcher = self.matcher.write().expect("route table lock poisoned");
+
+        let mut routes = matcher.routes();
+        edit(&mut routes)?;
+        for route in &mut routes {
+            resolve_upstream(route, &self.upstreams)?;
+        }
+        let compiled = RouteMatcher::new(routes).map_err(|e| e.to_string())?;
+
+        *matcher = Arc::new(compiled);
+        Ok(self.version.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    pub fn replace(&self, routes: Vec<RouteConfig>) -> Result<u64, String> {
+        self.update(|current| {
+            *current = routes;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MatchType;
+
+    fn route(path: &str, match_type: MatchType) -> RouteConfig {
+        RouteConfig {
+            path: path.to_string(),
+            target: "http://localhost:3000".to_string(),
+            match_type,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_update_swaps_matcher() {
+        let table = RouteTable::new(RouteMatcher::new(vec![]).unwrap());
+        let before = table.current();
+
+        let version = table
+            .update(|routes| {
+                routes.push(route("/api", MatchType::Prefix));
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(version, 2);
+        assert!(before.find_match("/api/users", None).is_none());
+        assert!(table.current().find_match("/api/users", None).is_some());
+    }
+
+    #[test]
+    fn test_failed_update_keeps_routes() {
+        let table =
+            RouteTable::new(RouteMatcher::new(vec![route("/api", MatchType::Prefix)]).unwrap());
+
+        let result = table.replace(vec![route("(unclosed", MatchType::Regex)]);
+
+        assert!(result.is_err());
+        assert_eq!(table.version(), 1);
+        assert!(table.current().find_match("/api", None).is_some());
+    }
+}
@@ -1,40 +1,92 @@
-use crate::config::RouteConfig;
+use crate::config::{CompressionConfig, CorsConfig, ErrorPagesConfig, RouteAction};
 use crate::server::{
+    access_log::MatchedRoute,
+    actions,
+    cache::ResponseCache,
+    compression::{compress_response, decompress_request},
+    cors,
     error::ServerError,
-    matcher::{RouteMatch, RouteMatcher},
-    proxy::ProxyClient,
+    error_pages,
+    explain::{Explainer, explain},
+    listener::ListenerName,
+    matcher::RouteMatch,
+    metrics::Metrics,
+    proxy::{ProxyClient, build_target_url},
+    query::rewrite_query,
+    route_table::RouteTable,
+    upstreams::UpstreamHealth,
 };
 use axum::{
+    Json,
     body::Body,
     extract::{Request, State},
-    http::Method,
-    response::Response,
+    http::{Method, Uri, header},
+    response::{IntoResponse, Response},
 };
-use std::sync::Arc;
-use tracing::debug;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{Span, debug};
 
 #[derive(Clone)]
 pub struct AppState {
-    pub matcher: Arc<RouteMatcher>,
+    pub routes: Arc<RouteTable>,
+    pub upstreams: Arc<UpstreamHealth>,
     pub proxy_client: Arc<ProxyClient>,
-    #[allow(dead_code)]
+    // Clients for named upstreams, used instead of the shared one
+    pub upstream_clients: Arc<HashMap<String, Arc<ProxyClient>>>,
+    pub cache: Option<Arc<ResponseCache>>,
+    pub compression: Arc<CompressionConfig>,
+    pub cors: Arc<CorsConfig>,
+    pub metrics: Option<Arc<Metrics>>,
+    pub explainer: Option<Arc<Explainer>>,
+    pub error_pages: Arc<ErrorPagesConfig>,
+    // Error responses include details only in debug mode
     pub debug: bool,
 }
 
-pub async fn handle_request(
-    State(state): State<AppState>,
-    request: Request,
-) -> Result<Response<Body>, ServerError> {
+pub async fn handle_request(State(state): State<AppState>, request: Request) -> Response {
+    match route_request(&state, request).await {
+        Ok(response) => response,
+        // Errors before a route was picked use the global pages
+        Err(e) => error_pages::render(&state.error_pages, state.debug, e.into_response()).await,
+    }
+}
+
+async fn route_request(state: &AppState, request: Request) -> Result<Response<Body>, ServerError> {
     let method = request.method();
     let path = request.uri().path();
 
     debug!("Incoming request: {} {}", method, path);
 
-    // Find matching route using the new matcher
-    let route_match = state
-        .matcher
-        .find_match(path)
+    // Routes can be limited to some of the listeners
+    let listener = request.extensions().get::<ListenerName>();
+    let listener = listener.map(|ListenerName(name)| &**name);
+    let matcher = state.routes.current();
+
+    // Trusted callers can ask how a request would be routed without sending it
+    if let Some(explainer) = &state.explainer
+        && explainer.requested(&request)
+    {
+        let explanation = explain(&matcher, listener, method, request.uri(), request.headers());
+        return Ok(Json(explanation).into_response());
+    }
+
+    let route_match = matcher
+        .find_match_on(listener, path, request.uri().query())
         .ok_or(ServerError::RouteNotFound)?;
+    Span::current().record("http.route", route_match.route.path.as_str());
+
+    // Route-level CORS policy replaces the global one
+    let cors_config = route_match.route.cors.as_ref().unwrap_or(&state.cors);
+
+    // Answer preflights before method checks, OPTIONS is rarely a route method
+    if cors_config.enabled && cors::is_preflight(method, request.headers()) {
+        debug!("Answering CORS preflight for {}", path);
+        return Ok(cors::preflight_response(
+            cors_config,
+            request.headers(),
+            &route_match.route.methods,
+        ));
+    }
 
     // Check if method is allowed
     if !is_method_allowed(&route_match.route.methods, method) {
@@ -42,18 +94,97 @@ pub async fn handle_request(
     }
 
     debug!(
-        "Matched route: {} -> {} (params: {:?})",
-        route_match.route.path, route_match.route.target, route_match.params
+        "Matched route: {} -> {} (params: {:?}, source: {:?})",
+        route_match.route.path,
+        route_match.route.upstream_target(),
+        route_match.params,
+        route_match.route.source
     );
 
-    // TODO: Use route_match.params for path parameter substitution in target URL
-    state
-        .proxy_client
-        .proxy_request(request, &route_match.route.target)
-        .await
+    let method = method.clone();
+    let accept_encoding = request.headers().get(header::ACCEPT_ENCODING).cloned();
+    let origin = request.headers().get(header::ORIGIN).cloned();
+
+    let result = dispatch(state, &route_match, request).await;
+
+    if matches!(route_match.route.action, RouteAction::Proxy) {
+        let upstream = route_match.route.upstream_key();
+        state.upstreams.record(upstream, &result);
+        if let Some(metrics) = &state.metrics {
+            metrics.observe_upstream(upstream, &result);
+        }
+    }
+
+    // Errors still get CORS headers and route details for logging
+    let mut response = result.unwrap_or_else(IntoResponse::into_response);
+    let error_pages = route_match
+        .route
+        .errors
+        .as_ref()
+        .unwrap_or(&state.error_pages);
+    response = error_pages::render(error_pages, state.debug, response).await;
+
+    if cors_config.enabled {
+        cors::decorate_response(cors_config, origin.as_ref(), &mut response);
+    }
+
+    if state.compression.enabled && route_match.route.compression.enabled {
+        response = compress_response(
+            response,
+            &state.compression,
+            &method,
+            accept_encoding.as_ref(),
+        )
+        .await?;
+    }
+
+    response.extensions_mut().insert(MatchedRoute {
+        path: route_match.route.path.clone(),
+        target: route_match.route.upstream_target().to_string(),
+    });
+
+    Ok(response)
+}
+
+async fn dispatch(
+    state: &AppState,
+    route_match: &RouteMatch,
+    mut request: Request,
+) -> Result<Response<Body>, ServerError> {
+    if !matches!(route_match.route.action, RouteAction::Proxy) {
+        return actions::respond(&route_match.route.action, route_match, request).await;
+    }
+
+    if route_match.route.compression.decompress_requests {
+        request = decompress_request(request, state.compression.max_request_size).await?;
+    }
+
+    // Apply query string rules before forwarding
+    if route_match.route.query.has_rewrites() {
+        *request.uri_mut() = rewritten_uri(route_match, request.uri())?;
+    }
+
+    // Substitute path parameters into the target URL
+    let target = route_match.render(route_match.route.upstream_target());
+    let client = route_match
+        .route
+        .upstream
+        .as_ref()
+        .and_then(|name| state.upstream_clients.get(name))
+        .map(Arc::as_ref)
+        .unwrap_or(&state.proxy_client);
+
+    if let Some(cache) = &state.cache
+        && route_match.route.cache.enabled
+    {
+        let ttl = route_match.route.cache.ttl.map(Duration::from_secs);
+        return cache.fetch(client, request, &target, ttl).await;
+    }
+
+    client.proxy_request(request, &target).await
 }
 
-fn is_method_allowed(allowed_methods: &[String], method: &Method) -> bool {
+pub fn is_method_allowed(allowed_methods: &[String], method: &Method) -> bool {
     if allowed_methods.is_empty() {
         // If no methods specified, allow all
         return true;
@@ -65,9 +196,41 @@ fn is_method_allowed(allowed_methods: &[String], method: &Method) -> bool {
         .any(|m| m.eq_ignore_ascii_case(method_str))
 }
 
-// Health check endpoint - not part of configured routes
-pub async fn health_check() -> &'static str {
-    "OK"
+/// Where a proxy route would send a request, after query rewrites
+pub fn upstream_url(route_match: &RouteMatch, uri: &Uri) -> Result<String, ServerError> {
+    let uri = rewritten_uri(route_match, uri)?;
+    build_target_url(
+        &route_match.render(route_match.route.upstream_target()),
+        &uri,
+    )
+}
+
+fn rewritten_uri(route_match: &RouteMatch, uri: &Uri) -> Result<Uri, ServerError> {
+    let query_config = &route_match.route.query;
+    if !query_config.has_rewrites() {
+        return Ok(uri.clone());
+    }
+
+    let query = rewrite_query(query_config, uri.query(), route_match);
+    debug!("Rewritten query: {:?}", query);
+    replace_query(uri, query.as_deref())
+}
+
+fn replace_query(uri: &Uri, query: Option<&str>) -> Result<Uri, ServerError> {
+    let path_and_query = match query {
+        Some(q) => format!("{}?{}", uri.path(), q),
+        None => uri.path().to_string(),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(
+        path_and_query
+            .parse()
+            .map_err(|e| ServerError::RequestError(format!("Invalid query string: {}", e)))?,
+    );
+
+    Uri::from_parts(parts)
+        .map_err(|e| ServerError::InternalError(format!("Failed to rebuild URI: {}", e)))
 }
 
 #[cfg(test)]
@@ -86,4 +249,37 @@ mod tests {
         // Empty methods list should allow all
         assert!(is_method_allowed(&[], &Method::DELETE));
     }
+
+    #[test]
+    fn test_upstream_url() {
+        use crate::config::{MatchType, RouteConfig};
+        use crate::server::matcher::RouteMatcher;
+
+        let mut route = RouteConfig {
+            path: "/users/{id}".to_string(),
+            target: "http://users/{id}".to_string(),
+            match_type: MatchType::Wildcard,
+            ..Default::default()
+        };
+        route.query.remove = vec!["token".to_string()];
+        let matcher = RouteMatcher::new(vec![route]).unwrap();
+
+        let uri: Uri = "/users/7?token=x&q=1".parse().unwrap();
+        let route_match = matcher.find_match(uri.path(), uri.query()).unwrap();
+        assert_eq!(
+            upstream_url(&route_match, &uri).unwrap(),
+            "http://users/7/users/7?q=1"
+        );
+    }
+
+    #[test]
+    fn test_replace_query() {
+        let uri: Uri = "/search?q=1&token=x".parse().unwrap();
+
+        assert_eq!(
+            replace_query(&uri, Some("q=1")).unwrap().to_string(),
+            "/search?q=1"
+        );
+        assert_eq!(replace_query(&uri, None).unwrap().to_string(), "/search");
+    }
 }
@@ -0,0 +1,251 @@
+use crate::server::{body::on_body_end, health::Health};
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::IntGauge;
+use std::{
+    future::Future,
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::{signal, sync::watch, task::JoinHandle};
+use tracing::{error, info, warn};
+
+/// Tells the server to stop accepting connections and start draining
+#[derive(Clone)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (tx, _) = watch::channel(false);
+        Self { tx: Arc::new(tx) }
+    }
+
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    /// Resolves once `trigger` has been called
+    pub fn triggered(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.tx.subscribe();
+        async move {
+            let _ = rx.wait_for(|triggered| *triggered).await;
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Requests currently being handled, reported when draining and exported
+/// as a metric when enabled
+#[derive(Default)]
+pub struct InFlight {
+    count: AtomicUsize,
+    gauge: Option<IntGauge>,
+}
+
+impl InFlight {
+    pub fn new(gauge: Option<IntGauge>) -> Self {
+        Self {
+            count: AtomicUsize::new(0),
+            gauge,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+}
+
+// Counts a request until its response body is sent, or until either is dropped
+// because the client went away
+struct InFlightGuard(Arc<InFlight>);
+
+impl InFlightGuard {
+    fn new(in_flight: Arc<InFlight>) -> Self {
+        in_flight.count.fetch_add(1, Ordering::SeqCst);
+        if let Some(gauge) = &in_flight.gauge {
+            gauge.inc();
+        }
+        Self(in_flight)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.count.fetch_sub(1, Ordering::SeqCst);
+        if let Some(gauge) = &self.0.gauge {
+            gauge.dec();
+        }
+    }
+}
+
+pub async fn in_flight_middleware(
+    State(in_flight): State<Arc<InFlight>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let guard = InFlightGuard::new(in_flight);
+    let response = next.run(request).await;
+    on_body_end(response, move |_| drop(guard))
+}
+
+pub struct DrainSettings {
+    pub delay: Duration,
+    pub timeout: Duration,
+}
+
+/// Run the server until it fails or a shutdown signal arrives, then drain
+pub async fn serve_until_signal(
+    mut server: JoinHandle<io::Result<()>>,
+    shutdown: Shutdown,
+    health: Arc<Health>,
+    in_flight: Arc<InFlight>,
+    settings: DrainSettings,
+) -> Result<()> {
+    let signal = tokio::select! {
+        result = &mut server => return flatten(result),
+        signal = wait_for_signal() => signal,
+    };
+
+    info!(
+        "Received {}, shutting down ({} requests in flight)",
+        signal,
+        in_flight.count()
+    );
+    health.set_shutting_down();
+
+    if !settings.delay.is_zero() {
+        info!("Waiting {:?} before closing the listener", settings.delay);
+        tokio::time::sleep(settings.delay).await;
+    }
+
+    let started = Instant::now();
+    shutdown.trigger();
+
+    match tokio::time::timeout(settings.timeout, &mut server).await {
+        Ok(result) => {
+            flatten(result)?;
+            info!(
+                "Shutdown complete: drained all requests in {:.1}s",
+                started.elapsed().as_secs_f64()
+            );
+        }
+        Err(_) => {
+            server.abort();
+            warn!(
+                "Shutdown complete: drain timeout of {:?} reached, abandoned {} in-flight requests",
+                settings.timeout,
+                in_flight.count()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn flatten(result: Result<io::Result<()>, tokio::task::JoinError>) -> Result<()> {
+    match result {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => {
+            error!("Server error: {}", e);
+            Err(e.into())
+        }
+        Err(e) => {
+            error!("Server task failed: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+async fn wait_for_signal() -> &'static str {
+    let interrupt = async {
+        if let Err(e) = signal::ctrl_c().await {
+            error!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => "SIGINT",
+        _ = terminate => "SIGTERM",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_trigger() {
+        let shutdown = Shutdown::new();
+        let triggered = tokio::spawn(shutdown.triggered());
+
+        tokio::task::yield_now().await;
+        assert!(!triggered.is_finished());
+
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(1), triggered)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Late subscribers see the trigger too
+        tokio::time::timeout(Duration::from_secs(1), shutdown.triggered())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_until_body_ends() {
+        let gauge = IntGauge::new("in_flight", "in flight").unwrap();
+        let in_flight = Arc::new(InFlight::new(Some(gauge.clone())));
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(|| async { "body" }))
+            .layer(axum::middleware::from_fn_with_state(
+                in_flight.clone(),
+                in_flight_middleware,
+            ));
+
+        let response = tower::ServiceExt::oneshot(app, Request::new(axum::body::Body::empty()))
+            .await
+            .unwrap();
+        // Headers are out but the body hasn't been sent yet
+        assert_eq!(in_flight.count(), 1);
+        assert_eq!(gauge.get(), 1);
+
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(in_flight.count(), 0);
+        assert_eq!(gauge.get(), 0);
+    }
+}
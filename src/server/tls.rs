@@ -0,0 +1,103 @@
+use crate::config::TlsConfig;
+use anyhow::{Context, Result, anyhow};
+use axum::serve::Listener;
+use std::{fs::File, io, io::BufReader, sync::Arc, time::Duration};
+use tokio::sync::mpsc;
+use tokio_rustls::{TlsAcceptor, rustls, server::TlsStream};
+use tracing::debug;
+
+// Slow or stalled handshakes are dropped after this long
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Acceptor for the configured certificate, offering HTTP/2 and HTTP/1.1
+pub fn acceptor(config: &TlsConfig) -> Result<TlsAcceptor> {
+    let cert_file = File::open(&config.cert)
+        .with_context(|| format!("Failed to open {}", config.cert.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(cert_file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid certificate in {}", config.cert.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificate found in {}", config.cert.display()));
+    }
+
+    let key_file = File::open(&config.key)
+        .with_context(|| format!("Failed to open {}", config.key.display()))?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(key_file))
+        .with_context(|| format!("Invalid private key in {}", config.key.display()))?
+        .ok_or_else(|| anyhow!("No private key found in {}", config.key.display()))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut server_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("Certificate and key do not match")?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// Yields connections once their TLS handshake has completed. Handshakes run
+/// in their own tasks so a slow client cannot hold up other accepts.
+pub struct TlsListener<L: Listener> {
+    connections: mpsc::Receiver<(TlsStream<L::Io>, L::Addr)>,
+    local_addr: L::Addr,
+}
+
+impl<L> TlsListener<L>
+where
+    L: Listener,
+    L::Addr: Clone + Sync + 'static,
+{
+    pub fn new(mut inner: L, acceptor: TlsAcceptor) -> io::Result<Self> {
+        let local_addr = inner.local_addr()?;
+        let (tx, connections) = mpsc::channel(64);
+
+        tokio::spawn(async move {
+            loop {
+                // Stop accepting once the server has dropped this listener
+                let (io, addr) = tokio::select! {
+                    _ = tx.closed() => break,
+                    accepted = inner.accept() => accepted,
+                };
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(io)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send((stream, addr)).await;
+                        }
+                        Ok(Err(e)) => debug!("TLS handshake failed: {}", e),
+                        Err(_) => debug!("TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            connections,
+            local_addr,
+        })
+    }
+}
+
+impl<L> Listener for TlsListener<L>
+where
+    L: Listener,
+    L::Addr: Clone + Sync + 'static,
+{
+    type Io = TlsStream<L::Io>;
+    type Addr = L::Addr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept task outlives the receiver, so this is never reached
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr.clone())
+    }
+}
@@ -0,0 +1,21 @@
+use axum::http::HeaderMap;
+use opentelemetry::global;
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Continue the caller's trace, if the request carries one
+pub fn continue_trace(span: &Span, headers: &HeaderMap) {
+    let parent =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    // Fails only when trace export is disabled, in which case there is nothing to link
+    let _ = span.set_parent(parent);
+}
+
+/// Write the span's trace context into outgoing headers, replacing the caller's
+pub fn inject_trace(span: &Span, headers: &mut HeaderMap) {
+    let context = span.context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
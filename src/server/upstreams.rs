@@ -0,0 +1,198 @@
+use crate::config::UpstreamConfig;
+use crate::server::{error::ServerError, proxy::ProxyClient};
+use axum::{body::Body, response::Response};
+use chrono::Utc;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::{debug, info};
+
+// Consecutive failed calls before an upstream is reported unhealthy
+const UNHEALTHY_AFTER: u32 = 3;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpstreamState {
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamStatus {
+    /// Name of the upstream, or the target of routes without one
+    pub upstream: String,
+    pub state: UpstreamState,
+    pub requests: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_success: Option<String>,
+    pub last_failure: Option<String>,
+}
+
+impl UpstreamStatus {
+    fn new(upstream: &str) -> Self {
+        Self {
+            upstream: upstream.to_string(),
+            state: UpstreamState::Unknown,
+            requests: 0,
+            failures: 0,
+            consecutive_failures: 0,
+            last_error: None,
+            last_success: None,
+            last_failure: None,
+        }
+    }
+}
+
+/// Upstream health, derived from the outcome of proxied requests and of
+/// health checks
+#[derive(Default)]
+pub struct UpstreamHealth {
+    upstreams: Mutex<HashMap<String, UpstreamStatus>>,
+}
+
+impl UpstreamHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, upstream: &str, result: &Result<Response<Body>, ServerError>) {
+        match result {
+            Err(ServerError::ProxyError(msg)) => self.update(upstream, Some(msg)),
+            // Anything else means the upstream answered
+            _ => self.update(upstream, None),
+        }
+    }
+
+    pub fn record_check(&self, upstream: &str, result: &Result<(), String>) {
+        self.update(upstream, result.as_ref().err());
+    }
+
+    fn update(&self, upstream: &str, failure: Option<&String>) {
+        let mut upstreams = self
+            .upstreams
+            .lock()
+            .expect("upstream health lock poisoned");
+        let status = upstreams
+            .entry(upstream.to_string())
+            .or_insert_with(|| UpstreamStatus::new(upstream));
+        let now = Utc::now().to_rfc3339();
+
+        status.requests += 1;
+        match failure {
+            Some(msg) => {
+                status.failures += 1;
+                status.consecutive_failures += 1;
+                status.last_error = Some(msg.clone());
+                status.last_failure = Some(now);
+                if status.consecutive_failures >= UNHEALTHY_AFTER {
+                    status.state = UpstreamState::Unhealthy;
+                }
+            }
+            None => {
+                status.consecutive_failures = 0;
+                status.last_success = Some(now);
+                status.state = UpstreamState::Healthy;
+            }
+        }
+    }
+
+    /// Status of the given upstreams; ones without traffic yet are reported as unknown
+    pub fn status(&self, upstreams: &[String]) -> Vec<UpstreamStatus> {
+        let recorded = self
+            .upstreams
+            .lock()
+            .expect("upstream health lock poisoned");
+        upstreams
+            .iter()
+            .map(|upstream| {
+                recorded
+                    .get(upstream)
+                    .cloned()
+                    .unwrap_or_else(|| UpstreamStatus::new(upstream))
+            })
+            .collect()
+    }
+}
+
+/// Probe every upstream that has a health check, for as long as the server runs
+pub fn spawn_health_checks(
+    upstreams: &HashMap<String, UpstreamConfig>,
+    clients: &HashMap<String, Arc<ProxyClient>>,
+    health: &Arc<UpstreamHealth>,
+) {
+    for (name, upstream) in upstreams {
+        let (Some(check), Some(client)) = (&upstream.health_check, clients.get(name)) else {
+            continue;
+        };
+
+        let url = format!(
+            "{}{}",
+            upstream.url.trim_end_matches('/'),
+            if check.path.starts_with('/') { "" } else { "/" }
+        ) + &check.path;
+        let interval = Duration::from_secs(check.interval_secs);
+        let timeout = Duration::from_secs(check.timeout_secs);
+        let (name, client, health) = (name.clone(), client.clone(), health.clone());
+
+        info!(
+            "Health checking upstream {} at {} every {:?}",
+            name, url, interval
+        );
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let result = client.check(&url, timeout).await;
+                if let Err(e) = &result {
+                    debug!("Health check of upstream {} failed: {}", name, e);
+                }
+                health.record_check(&name, &result);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consecutive_failures() {
+        let health = UpstreamHealth::new();
+        let target = "users".to_string();
+        let failure = || Err(ServerError::ProxyError("connection refused".to_string()));
+
+        for _ in 0..UNHEALTHY_AFTER - 1 {
+            health.record(&target, &failure());
+        }
+        assert_eq!(
+            health.status(std::slice::from_ref(&target))[0].state,
+            UpstreamState::Unknown
+        );
+
+        health.record(&target, &failure());
+        let status = &health.status(std::slice::from_ref(&target))[0];
+        assert_eq!(status.state, UpstreamState::Unhealthy);
+        assert_eq!(status.last_error.as_deref(), Some("connection refused"));
+
+        health.record(&target, &Ok(Response::new(Body::empty())));
+        let status = &health.status(std::slice::from_ref(&target))[0];
+        assert_eq!(status.state, UpstreamState::Healthy);
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!(status.requests, UNHEALTHY_AFTER as u64 + 1);
+
+        // Health checks count like proxied requests
+        for _ in 0..UNHEALTHY_AFTER {
+            health.record_check(&target, &Err("returned 503".to_string()));
+        }
+        let status = &health.status(std::slice::from_ref(&target))[0];
+        assert_eq!(status.state, UpstreamState::Unhealthy);
+        assert_eq!(status.last_error.as_deref(), Some("returned 503"));
+    }
+}